@@ -0,0 +1,81 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// n's own cache directory, never the package manager's
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache_home = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+    Some(cache_home.join("n"))
+}
+
+// Deletes the cache directory and returns how many files and bytes were removed
+pub fn reset_cache() -> Result<(usize, u64), String> {
+    let dir = cache_dir().ok_or("could not determine the cache directory")?;
+    remove_cache_dir(&dir)
+}
+
+fn remove_cache_dir(dir: &Path) -> Result<(usize, u64), String> {
+    // Refuse to touch anything that isn't a plain directory named after us
+    match fs::symlink_metadata(dir) {
+        Err(_) => return Ok((0, 0)),
+        Ok(metadata) if !metadata.is_dir() || dir.file_name() != Some("n".as_ref()) => {
+            return Err(format!("refusing to remove {}", dir.display()));
+        }
+        Ok(_) => {}
+    }
+
+    let totals = measure(dir);
+    fs::remove_dir_all(dir).map_err(|e| format!("failed to remove {}: {}", dir.display(), e))?;
+
+    Ok(totals)
+}
+
+// Counts files and bytes without following symlinks out of the directory
+fn measure(dir: &Path) -> (usize, u64) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (0, 0);
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some((entry.path(), fs::symlink_metadata(entry.path()).ok()?)))
+        .fold((0, 0), |(files, bytes), (path, metadata)| {
+            if metadata.is_dir() {
+                let (inner_files, inner_bytes) = measure(&path);
+                (files + inner_files, bytes + inner_bytes)
+            } else {
+                (files + 1, bytes + metadata.len())
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn removes_the_populated_cache_dir() {
+        let home = TempDir::new();
+        let dir = home.path().join("n");
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("debounce-1"), "1234").unwrap();
+        fs::write(dir.join("nested/entry"), "ab").unwrap();
+
+        assert_eq!(remove_cache_dir(&dir), Ok((2, 6)));
+        assert!(!dir.exists());
+        assert_eq!(remove_cache_dir(&dir), Ok((0, 0)));
+    }
+
+    #[test]
+    fn refuses_directories_not_named_n() {
+        let home = TempDir::new();
+
+        assert!(remove_cache_dir(home.path()).is_err());
+        assert!(home.path().is_dir());
+    }
+}
@@ -0,0 +1,155 @@
+// Narrows recursive runs to the workspace packages git reports changes in.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::Error;
+use crate::workspace::WorkspacePackage;
+
+// What `--since` compares against when no ref is given
+pub const DEFAULT_REF: &str = "@{upstream}";
+
+// Takes `--since <ref>` (or `--since=<ref>`) out of `args`. A bare `--since` means the
+// upstream branch.
+pub fn take_since(args: &[String]) -> (Option<String>, Vec<String>) {
+    let mut since = None;
+    let mut remaining = Vec::new();
+    let mut iter = args.iter().peekable();
+
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            remaining.push(arg.clone());
+            remaining.extend(iter.by_ref().cloned());
+        } else if arg == "--since" {
+            let value = iter.next_if(|next| !next.starts_with('-'));
+            since = Some(value.cloned().unwrap_or_else(|| DEFAULT_REF.to_string()));
+        } else if let Some(value) = arg.strip_prefix("--since=") {
+            since = Some(value.to_string());
+        } else {
+            remaining.push(arg.clone());
+        }
+    }
+
+    (since, remaining)
+}
+
+// Files changed since `since`, relative to `root`
+pub fn changed_files(root: &Path, since: &str) -> Result<Vec<PathBuf>, Error> {
+    let output = Command::new("git")
+        .args(["diff", "--name-only", "--relative", since])
+        .current_dir(root)
+        .output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => Error::ProgramNotFound("git".to_string()),
+            _ => Error::Other(format!("failed to run git: {}", e)),
+        })?;
+
+    if !output.status.success() {
+        return Err(Error::Other(format!(
+            "`git diff {}` failed: {}",
+            since,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+// Keeps the packages owning one of `changed`. A file belongs to the innermost package
+// around it, so changes in a nested package don't mark its parent (or the root).
+pub fn filter_changed(
+    packages: Vec<WorkspacePackage>,
+    root: &Path,
+    changed: &[PathBuf],
+) -> Vec<WorkspacePackage> {
+    let relative: Vec<PathBuf> = packages
+        .iter()
+        .map(|package| {
+            package
+                .dir
+                .strip_prefix(root)
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|_| package.dir.clone())
+        })
+        .collect();
+
+    let owners: Vec<usize> = changed
+        .iter()
+        .filter_map(|file| {
+            relative
+                .iter()
+                .enumerate()
+                .filter(|(_, dir)| file.starts_with(dir))
+                .max_by_key(|(_, dir)| dir.components().count())
+                .map(|(index, _)| index)
+        })
+        .collect();
+
+    packages
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| owners.contains(index))
+        .map(|(_, package)| package)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(root: &Path, dir: &str) -> WorkspacePackage {
+        WorkspacePackage {
+            name: dir.rsplit('/').next().unwrap_or(dir).to_string(),
+            dir: root.join(dir),
+        }
+    }
+
+    fn names(packages: &[WorkspacePackage]) -> Vec<&str> {
+        packages
+            .iter()
+            .map(|package| package.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn changed_files_pick_their_innermost_package() {
+        let root = Path::new("/repo");
+        let packages = vec![
+            package(root, ""),
+            package(root, "packages/web"),
+            package(root, "packages/web/plugins/seo"),
+            package(root, "packages/api"),
+            package(root, "packages/ui"),
+        ];
+        let changed = [
+            "packages/web/plugins/seo/index.ts",
+            "packages/api/src/server.ts",
+            "packages/api/package.json",
+        ]
+        .map(PathBuf::from);
+
+        let selected = filter_changed(packages, root, &changed);
+
+        assert_eq!(names(&selected), ["seo", "api"]);
+    }
+
+    #[test]
+    fn since_defaults_to_the_upstream_branch() {
+        let args =
+            |line: &str| -> Vec<String> { line.split_whitespace().map(str::to_string).collect() };
+
+        assert_eq!(
+            take_since(&args("--since main --bail")),
+            (Some("main".to_string()), args("--bail"))
+        );
+        assert_eq!(
+            take_since(&args("--since --bail")),
+            (Some(DEFAULT_REF.to_string()), args("--bail"))
+        );
+    }
+}
@@ -0,0 +1,89 @@
+// `n remove --all` empties `dependencies` and `devDependencies` through the manager,
+// so its lockfile and node_modules follow along
+
+use std::path::Path;
+
+use crate::cli::Options;
+use crate::commands::patch_commands;
+use crate::config::Config;
+use crate::error::Error;
+use crate::package_manager::PackageManager;
+use crate::runner::{ask, ensure_allowed, run_manager, CommandRunner, CommandSpec};
+use crate::shell;
+use crate::workspace::read_dependencies;
+
+// Keeps each command line well below the limits of any shell
+const CHUNK_SIZE: usize = 50;
+
+// `args` are the flags besides `--all` and `--reinstall`, passed to every remove
+pub fn remove_all(
+    runner: &dyn CommandRunner,
+    manager: PackageManager,
+    manifest: &Path,
+    args: &[String],
+    reinstall: bool,
+    config: &Config,
+    options: &Options,
+) -> Result<(), Error> {
+    let dependencies = read_dependencies(manifest);
+    if dependencies.is_empty() {
+        println!("{} has no dependencies to remove", manifest.display());
+        return Ok(());
+    }
+
+    let dir = manifest.parent().unwrap_or(Path::new("."));
+    let prints = options.print_command || options.print;
+
+    if !prints {
+        let prompt = format!(
+            "Remove all {} dependencies from {}?",
+            dependencies.len(),
+            manifest.display()
+        );
+        match ask(&prompt, options.yes) {
+            Some(true) => {}
+            Some(false) => return Err(Error::Aborted),
+            None => {
+                return Err(Error::Usage(
+                    "`remove --all` needs --yes without a terminal to confirm on".to_string(),
+                ))
+            }
+        }
+    }
+
+    let removes = dependencies
+        .chunks(CHUNK_SIZE)
+        .map(|chunk| [vec!["remove".to_string()], chunk.to_vec(), args.to_vec()].concat());
+    let install = reinstall.then(|| vec!["install".to_string()]);
+
+    for command in removes.chain(install) {
+        let patched = patch_commands(manager, &command, config);
+        ensure_allowed(&command, &patched, &config.deny)?;
+
+        let program = config.binary(manager);
+        let argv = [options.manager_args.clone(), patched].concat();
+
+        if options.print {
+            println!("{}", shell::argv_json(program, &argv));
+            continue;
+        }
+        if options.print_command {
+            println!("{}", shell::command_line(program, &argv));
+            continue;
+        }
+
+        let spec = CommandSpec {
+            program: program.to_string(),
+            args: argv,
+            dir: dir.to_path_buf(),
+            timeout: options.timeout,
+            spinner: false,
+            env: Vec::new(),
+            prefix: None,
+            bail_on: None,
+        };
+        run_manager(runner, manager, &spec)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,212 @@
+// Flags meant for n itself. They have to come before the command, everything
+// from the first unrecognized argument on is forwarded to the package manager.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::package_manager::PackageManager;
+use crate::pool::Concurrency;
+use crate::shell;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    // Wait this long and only run if no identical invocation came in meanwhile
+    pub after: Option<Duration>,
+    pub profile: Option<String>,
+    // Run in the directory the package manager was detected in instead of the cwd
+    pub at_root: bool,
+    pub dump_config: bool,
+    pub timeout: Option<Duration>,
+    // Run unknown commands through dlx instead of handing them to the manager
+    pub dlx_fallback: bool,
+    // Answer yes to every confirmation prompt
+    pub yes: bool,
+    // Print the resolved command as a shell line instead of running it
+    pub print_command: bool,
+    // Print the resolved argv as a JSON array instead of running it
+    pub print: bool,
+    // Choose the script to run from a list
+    pub pick: bool,
+    // Print the detected manager's name and exit
+    pub print_manager: bool,
+    // Run the script in every workspace package
+    pub recursive: bool,
+    // Also run the root package's script in recursive runs
+    pub include_root: bool,
+    // How many projects or packages run at the same time, None is the same as `auto`
+    pub concurrency: Option<Concurrency>,
+    // Use this manifest instead of ./package.json, its directory becomes the base
+    pub package_json: Option<PathBuf>,
+    // Report errors as JSON on stderr
+    pub json: bool,
+    // Show a spinner instead of the output of installs, unless they fail
+    pub spinner: bool,
+    // Passed to the manager before the command
+    pub manager_args: Vec<String>,
+    // Skip detection and use this manager
+    pub manager: Option<PackageManager>,
+    // Run the manager through corepack at this `name@version`, implies `manager`
+    pub manager_version: Option<String>,
+    // Explain what n is doing on stderr
+    pub verbose: bool,
+    // Keep running and print the manager every time detection changes
+    pub watch_detect: bool,
+    // Read the command's arguments from stdin, appended to the ones given here
+    pub stdin_args: bool,
+    // Take the scripts from a package.json on stdin instead of the one on disk
+    pub scripts_from_stdin: bool,
+    // Fail scripts that print `warning_marker`, even when they exit with 0
+    pub bail_on_warning: bool,
+    // Write a JSON line per detection and run step, to the file or else to stderr
+    pub trace: Option<Option<PathBuf>>,
+}
+
+pub fn parse_args(args: &[String]) -> Result<(Options, Vec<String>), String> {
+    let mut options = Options::default();
+    let mut index = 0;
+
+    while let Some(arg) = args.get(index) {
+        // Value flags accept both `--flag value` and `--flag=value`
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+
+        match name {
+            "--after" => {
+                let value = flag_value(args, &mut index, inline_value, name)?;
+                options.after = Some(parse_duration(&value)?);
+            }
+            "--at-root" => options.at_root = true,
+            "--bail-on-warning" => options.bail_on_warning = true,
+            "--concurrency" => {
+                let value = flag_value(args, &mut index, inline_value, name)?;
+                let concurrency = match value.as_str() {
+                    "auto" => Some(Concurrency::Auto),
+                    _ => value
+                        .parse()
+                        .ok()
+                        .filter(|concurrency| *concurrency > 0)
+                        .map(Concurrency::Fixed),
+                };
+                options.concurrency =
+                    Some(concurrency.ok_or_else(|| format!("invalid concurrency `{}`", value))?);
+            }
+            "--dlx-fallback" => options.dlx_fallback = true,
+            "--dump-config" => options.dump_config = true,
+            "--include-root" => options.include_root = true,
+            "--no-include-root" => options.include_root = false,
+            "--json" => options.json = true,
+            "--manager" => {
+                let value = flag_value(args, &mut index, inline_value, name)?;
+                let manager = PackageManager::from_name(&value)
+                    .ok_or_else(|| format!("unknown package manager `{}`", value))?;
+                options.manager = Some(manager);
+            }
+            "--manager-args" => {
+                let value = flag_value(args, &mut index, inline_value, name)?;
+                options.manager_args.extend(shell::split(&value)?);
+            }
+            "--manager-version" => {
+                let value = flag_value(args, &mut index, inline_value, name)?;
+                options.manager = Some(parse_manager_version(&value)?);
+                options.manager_version = Some(value);
+            }
+            "--package-json" => {
+                let value = flag_value(args, &mut index, inline_value, name)?;
+                options.package_json = Some(PathBuf::from(value));
+            }
+            "--pick" => options.pick = true,
+            "--print" => options.print = true,
+            "--print-command" => options.print_command = true,
+            "--print-manager" => options.print_manager = true,
+            "-r" | "--recursive" => options.recursive = true,
+            "--profile" => {
+                options.profile = Some(flag_value(args, &mut index, inline_value, name)?);
+            }
+            "--scripts-from-stdin" => options.scripts_from_stdin = true,
+            "--spinner" => options.spinner = true,
+            "--stdin-args" => options.stdin_args = true,
+            "-y" | "--yes" => options.yes = true,
+            "--timeout" => {
+                let value = flag_value(args, &mut index, inline_value, name)?;
+                options.timeout = Some(parse_duration(&value)?);
+            }
+            // Only `--trace=<file>` takes a file, a separate value would be the command
+            "--trace" => options.trace = Some(inline_value.map(PathBuf::from)),
+            "--verbose" => options.verbose = true,
+            // A lone `n -v` still asks the manager for its version
+            "-v" if index + 1 < args.len() => options.verbose = true,
+            "--watch-detect" => options.watch_detect = true,
+            _ => break,
+        }
+        index += 1;
+    }
+
+    if options.stdin_args && options.scripts_from_stdin {
+        return Err("`--stdin-args` and `--scripts-from-stdin` both read stdin".to_string());
+    }
+
+    Ok((options, args[index..].to_vec()))
+}
+
+// `pnpm@8.15.0`, for the managers corepack can run
+fn parse_manager_version(spec: &str) -> Result<PackageManager, String> {
+    let (name, _) = spec
+        .split_once('@')
+        .filter(|(_, version)| !version.is_empty())
+        .ok_or_else(|| format!("`{}` should look like `pnpm@8.15.0`", spec))?;
+
+    match PackageManager::from_name(name) {
+        Some(manager @ (PackageManager::Npm | PackageManager::Yarn | PackageManager::Pnpm)) => {
+            Ok(manager)
+        }
+        _ => Err(format!(
+            "corepack can only run npm, yarn or pnpm, not `{}`",
+            name
+        )),
+    }
+}
+
+// Parses durations like `500ms`, `30s`, `2m` or `1h`, bare numbers are seconds
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid duration `{}`", input))?;
+
+    let seconds = |per_unit: u64| {
+        amount
+            .checked_mul(per_unit)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("invalid duration `{}`", input))
+    };
+
+    match unit {
+        "ms" => Ok(Duration::from_millis(amount)),
+        "" | "s" => seconds(1),
+        "m" => seconds(60),
+        "h" => seconds(60 * 60),
+        _ => Err(format!("invalid duration unit in `{}`", input)),
+    }
+}
+
+fn flag_value(
+    args: &[String],
+    index: &mut usize,
+    inline_value: Option<String>,
+    flag: &str,
+) -> Result<String, String> {
+    if let Some(value) = inline_value {
+        return Ok(value);
+    }
+
+    *index += 1;
+    args.get(*index)
+        .cloned()
+        .ok_or_else(|| format!("`{}` expects a value", flag))
+}
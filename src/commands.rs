@@ -0,0 +1,1938 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+use crate::cli::parse_duration;
+use crate::config::{Config, DevAlias, OnAmbiguous};
+use crate::error::Error;
+use crate::json;
+use crate::log;
+use crate::package_manager::PackageManager;
+use crate::picker;
+use crate::scripts::{find_similar_command, find_similar_commands, has_normalized_twins};
+
+// Every spelling of "save as dev dependency" we accept from the user
+const DEV_FLAGS: &[&str] = &["-D", "--save-dev", "--dev"];
+
+// Scripts that the short aliases point at
+const SCRIPT_ALIASES: &[&str] = &["build", "dev", "start", "test"];
+
+// Variables CI providers set that the generic `CI` check would miss
+pub const CI_VARIABLES: &[&str] = &[
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "TRAVIS",
+    "JENKINS_URL",
+    "TF_BUILD",
+];
+
+const REMOVE_COMMANDS: &[&str] = &["remove", "rm", "uninstall", "un"];
+
+// Commands that install packages and therefore receive the configured install flags
+const INSTALL_COMMANDS: &[&str] = &["install", "add", "ci"];
+
+const NPM_COMMANDS: &[&str] = &[
+    "access",
+    "add",
+    "adduser",
+    "audit",
+    "bugs",
+    "cache",
+    "ci",
+    "completion",
+    "config",
+    "create",
+    "dedupe",
+    "deprecate",
+    "diff",
+    "dist-tag",
+    "docs",
+    "doctor",
+    "edit",
+    "exec",
+    "explain",
+    "explore",
+    "fund",
+    "help",
+    "info",
+    "init",
+    "install",
+    "link",
+    "login",
+    "logout",
+    "ls",
+    "outdated",
+    "owner",
+    "pack",
+    "patch",
+    "patch-commit",
+    "ping",
+    "pkg",
+    "prune",
+    "publish",
+    "query",
+    "rebuild",
+    "remove",
+    "repo",
+    "restart",
+    "rm",
+    "root",
+    "run",
+    "run-script",
+    "search",
+    "start",
+    "stop",
+    "team",
+    "test",
+    "uninstall",
+    "unpublish",
+    "update",
+    "version",
+    "view",
+    "whoami",
+];
+
+const YARN_COMMANDS: &[&str] = &[
+    "add",
+    "audit",
+    "autoclean",
+    "bin",
+    "cache",
+    "config",
+    "create",
+    "dedupe",
+    "dlx",
+    "exec",
+    "fund",
+    "global",
+    "help",
+    "import",
+    "info",
+    "init",
+    "install",
+    "licenses",
+    "link",
+    "list",
+    "login",
+    "logout",
+    "node",
+    "npm",
+    "outdated",
+    "owner",
+    "pack",
+    "patch",
+    "patch-commit",
+    "plugin",
+    "prune",
+    "publish",
+    "rebuild",
+    "remove",
+    "run",
+    "set",
+    "start",
+    "tag",
+    "team",
+    "test",
+    "unlink",
+    "unplug",
+    "up",
+    "upgrade",
+    "upgrade-interactive",
+    "version",
+    "versions",
+    "why",
+    "workspace",
+    "workspaces",
+];
+
+const PNPM_COMMANDS: &[&str] = &[
+    "add",
+    "audit",
+    "bin",
+    "config",
+    "create",
+    "dedupe",
+    "dlx",
+    "env",
+    "exec",
+    "fetch",
+    "fund",
+    "import",
+    "info",
+    "init",
+    "install",
+    "install-test",
+    "licenses",
+    "link",
+    "list",
+    "ls",
+    "outdated",
+    "pack",
+    "patch",
+    "patch-commit",
+    "prune",
+    "publish",
+    "rebuild",
+    "recursive",
+    "remove",
+    "rm",
+    "root",
+    "run",
+    "server",
+    "setup",
+    "start",
+    "store",
+    "test",
+    "unlink",
+    "up",
+    "update",
+    "version",
+    "why",
+];
+
+const BUN_COMMANDS: &[&str] = &[
+    "add",
+    "create",
+    "dedupe",
+    "exec",
+    "fund",
+    "info",
+    "init",
+    "install",
+    "link",
+    "outdated",
+    "patch",
+    "patch-commit",
+    "pm",
+    "prune",
+    "publish",
+    "rebuild",
+    "remove",
+    "repl",
+    "rm",
+    "run",
+    "unlink",
+    "update",
+    "upgrade",
+    "version",
+    "x",
+];
+
+const DENO_COMMANDS: &[&str] = &[
+    "add",
+    "approve-scripts",
+    "bench",
+    "bundle",
+    "cache",
+    "check",
+    "clean",
+    "compile",
+    "completions",
+    "coverage",
+    "doc",
+    "eval",
+    "fmt",
+    "fund",
+    "help",
+    "info",
+    "init",
+    "install",
+    "jupyter",
+    "lint",
+    "lsp",
+    "outdated",
+    "publish",
+    "remove",
+    "repl",
+    "run",
+    "serve",
+    "task",
+    "test",
+    "types",
+    "uninstall",
+    "update",
+    "upgrade",
+];
+
+pub fn patch_commands(manager: PackageManager, args: &[String], config: &Config) -> Vec<String> {
+    patch_commands_with(manager, args, config, is_ci())
+}
+
+fn patch_commands_with(
+    manager: PackageManager,
+    args: &[String],
+    config: &Config,
+    ci: bool,
+) -> Vec<String> {
+    let (bun_runtime, args) = take_flag(args, "--bun-runtime");
+
+    // bun only reads `--hot` and `--watch` in front of `run`. Other commands keep them,
+    // `bun x vite --hot` and `bun test --watch` have flags of the same name.
+    let script_run = runs_script(manager, &args, config);
+    let (runtime_flags, args) = if !script_run {
+        (Vec::new(), args)
+    } else if manager == PackageManager::Bun {
+        let (hot, args) = take_flag(&args, "--hot");
+        let (watch, args) = take_flag(&args, "--watch");
+        let flags = [(hot, "--hot"), (watch, "--watch")]
+            .into_iter()
+            .filter(|(present, _)| *present)
+            .map(|(_, flag)| flag.to_string())
+            .collect();
+        (flags, args)
+    } else {
+        let (hot, without_hot) = take_flag(&args, "--hot");
+        if hot {
+            eprintln!("{}", hot_flag_warning(manager));
+        }
+        // npm reads flags before `--` as its own config and would swallow it silently
+        if manager == PackageManager::Npm {
+            (Vec::new(), without_hot)
+        } else {
+            (Vec::new(), args)
+        }
+    };
+
+    let Some((first, rest)) = args.split_first() else {
+        return Vec::new();
+    };
+
+    let command = expand_alias(first);
+
+    if let Some(alias) = config.dev_alias.as_ref().filter(|_| first == "d") {
+        trace_alias(first, &alias.script);
+    } else if command != first {
+        trace_alias(first, command);
+    }
+
+    if command == "help" && !rest.is_empty() {
+        return patch_help_command(manager, rest);
+    }
+
+    // Taken out first so the url isn't mistaken for a package, every manager spells it the same
+    let (registry, rest) = if is_install_command(command) {
+        take_value_flag(rest, "--registry")
+    } else {
+        (None, rest.to_vec())
+    };
+
+    let (network_timeout, rest) = if is_install_command(command) {
+        take_value_flag(&rest, "--network-timeout")
+    } else {
+        (None, rest)
+    };
+
+    // A plain install is frozen in CI or with `--frozen`, `--no-frozen` always wins
+    let (frozen, rest) = if command == "install" {
+        let (forced, rest) = take_flag(&rest, "--frozen");
+        let (disabled, rest) = take_flag(&rest, "--no-frozen");
+        let installs_packages = rest.iter().any(|arg| !arg.starts_with('-'));
+        (!disabled && !installs_packages && (forced || ci), rest)
+    } else {
+        (false, rest)
+    };
+
+    let (production, rest) = if matches!(command, "install" | "ci") {
+        take_production_flags(&rest)
+    } else {
+        (false, rest)
+    };
+
+    // npm's own spelling is accepted too, it's the one people remember
+    let (lockfile_only, rest) = if command == "install" {
+        let (generic, rest) = take_flag(&rest, "--lockfile-only");
+        let (npm, rest) = take_flag(&rest, "--package-lock-only");
+        (generic || npm, rest)
+    } else {
+        (false, rest)
+    };
+
+    let rest = if matches!(command, "add" | "install") {
+        normalize_peer_flag(manager, &normalize_workspace_protocol(manager, &rest))
+    } else {
+        rest
+    };
+
+    // Forwarded anyway, the manager gives the better error
+    if command == "fund" && manager != PackageManager::Npm {
+        eprintln!("n: only npm has `fund`, {} will likely reject it", manager);
+    }
+    if matches!(command, "patch" | "patch-commit") && manager == PackageManager::Npm {
+        eprintln!("n: npm can't patch dependencies, try `npx patch-package` instead");
+    }
+
+    let mut patched = match &config.dev_alias {
+        Some(alias) if first == "d" => patch_dev_alias(manager, alias, &rest, config),
+        _ => match manager {
+            PackageManager::Npm => patch_npm_command(command, &rest),
+            PackageManager::Yarn => patch_yarn_command(command, &rest),
+            PackageManager::Pnpm => patch_pnpm_command(command, &rest),
+            PackageManager::Bun => patch_bun_command(command, &rest),
+            PackageManager::Deno => patch_deno_command(command, &rest),
+        },
+    };
+
+    // Writing the lockfile is the point, freezing it would make the manager refuse
+    if frozen && !lockfile_only {
+        match manager.frozen_flag() {
+            Some(flag)
+                if !patched
+                    .iter()
+                    .any(|arg| arg == flag || arg == "--immutable") =>
+            {
+                patched.push(flag.to_string())
+            }
+            Some(_) => {}
+            None => patched[0] = "ci".to_string(),
+        }
+    }
+
+    if patched
+        .first()
+        .is_some_and(|command| is_install_command(command))
+    {
+        for flag in &config.install_flags {
+            if !patched.contains(flag) {
+                patched.push(flag.clone());
+            }
+        }
+
+        if production {
+            match manager.production_flag() {
+                Some(flag) => patched.push(flag.to_string()),
+                None => eprintln!(
+                    "n: {} can't leave out dev dependencies, installing all of them",
+                    manager
+                ),
+            }
+        }
+
+        if lockfile_only {
+            match manager.lockfile_only_flag() {
+                Some(flag) => {
+                    if manager == PackageManager::Yarn {
+                        eprintln!("n: updating only the lockfile needs yarn 2 or later");
+                    }
+                    patched.push(flag.to_string());
+                }
+                None => eprintln!(
+                    "n: {} can't update the lockfile without installing, installing as usual",
+                    manager
+                ),
+            }
+        }
+
+        if let Some(timeout) = network_timeout {
+            push_network_timeout(manager, &timeout, &mut patched);
+        }
+
+        if let Some(registry) = registry {
+            patched.push("--registry".to_string());
+            patched.push(registry);
+        }
+
+        // Every manager spells it the same, so an explicit flag just passes through
+        let ignore_scripts = "--ignore-scripts".to_string();
+        if config.always_ignore_scripts && !patched.contains(&ignore_scripts) {
+            patched.push(ignore_scripts);
+        }
+    }
+
+    // Some berry plugin setups only find scripts through an explicit `run`
+    if manager == PackageManager::Yarn && config.yarn_force_run {
+        let runs_script = patched.first().is_some_and(|first| {
+            !first.starts_with('-') && !is_known_command(manager, first, config)
+        });
+        if runs_script {
+            patched.insert(0, "run".to_string());
+        }
+    }
+
+    let runs_bun_script = manager == PackageManager::Bun
+        && patched
+            .first()
+            .is_some_and(|command| command == "run" || !BUN_COMMANDS.contains(&command.as_str()));
+
+    if bun_runtime {
+        if manager != PackageManager::Bun {
+            eprintln!("n: --bun-runtime only applies to bun, ignoring it");
+        } else if runs_bun_script {
+            // Runtime flags have to come before `run`
+            patched.insert(0, "--bun".to_string());
+        }
+    }
+
+    if !runtime_flags.is_empty() {
+        if runs_bun_script {
+            patched.splice(0..0, runtime_flags);
+        } else {
+            eprintln!(
+                "n: {} only applies to scripts, ignoring it",
+                runtime_flags.join(" and ")
+            );
+        }
+    }
+
+    patched
+}
+
+// Whether `args` runs a package.json script rather than one of the manager's commands
+fn runs_script(manager: PackageManager, args: &[String], config: &Config) -> bool {
+    let Some(first) = args.first() else {
+        return false;
+    };
+    if first == "d" && config.dev_alias.is_some() {
+        return true;
+    }
+
+    match expand_alias(first) {
+        "run" | "run-script" => true,
+        command if SCRIPT_ALIASES.contains(&command) => true,
+        command => {
+            !command.starts_with('-')
+                && !is_known_command(manager, command, config)
+                && !is_install_command(command)
+                && !is_remove_command(command)
+                && !matches!(command, "dlx" | "exec" | "help")
+        }
+    }
+}
+
+pub fn known_commands(manager: PackageManager) -> &'static [&'static str] {
+    match manager {
+        PackageManager::Npm => NPM_COMMANDS,
+        PackageManager::Yarn => YARN_COMMANDS,
+        PackageManager::Pnpm => PNPM_COMMANDS,
+        PackageManager::Bun => BUN_COMMANDS,
+        PackageManager::Deno => DENO_COMMANDS,
+    }
+}
+
+// Built-in commands plus the ones added through `extra_known_commands`
+pub fn is_known_command(manager: PackageManager, command: &str, config: &Config) -> bool {
+    known_commands(manager).contains(&command)
+        || config
+            .extra_known_commands
+            .get(manager.name())
+            .is_some_and(|commands| commands.iter().any(|known| known == command))
+}
+
+pub fn is_install_command(command: &str) -> bool {
+    INSTALL_COMMANDS.contains(&command)
+}
+
+// The script `args` run, with aliases like `d` expanded and `run` skipped
+pub fn invoked_script(args: &[String]) -> Option<&str> {
+    match args.first()?.as_str() {
+        "run" => args.get(1).map(String::as_str),
+        first => Some(expand_alias(first)),
+    }
+}
+
+pub fn is_remove_command(command: &str) -> bool {
+    REMOVE_COMMANDS.contains(&command)
+}
+
+// One spelling per command, so `deny` and `confirm` can't be sidestepped with an alias.
+// Installing packages is `add` on some managers and `install` on others.
+pub fn canonical_command(command: &str) -> &str {
+    match command {
+        command if is_remove_command(command) => "remove",
+        "i" | "install" | "add" => "install",
+        command => command,
+    }
+}
+
+// Replaces a mistyped script name with the closest script from package.json.
+// Aliases, native commands and exact script names are left alone.
+pub fn try_autocorrect_script(
+    manager: PackageManager,
+    args: &[String],
+    scripts: &[String],
+    config: &Config,
+) -> Result<Vec<String>, Error> {
+    let stdin = io::stdin();
+    let mut terminal = stdin.is_terminal().then(|| stdin.lock());
+    let terminal = terminal.as_mut().map(|input| input as &mut dyn io::BufRead);
+    autocorrect_script_with(manager, args, scripts, config, terminal)
+}
+
+// `terminal` is where the pick between equally close scripts is read from, None when
+// nobody is there to ask
+fn autocorrect_script_with(
+    manager: PackageManager,
+    args: &[String],
+    scripts: &[String],
+    config: &Config,
+    terminal: Option<&mut dyn io::BufRead>,
+) -> Result<Vec<String>, Error> {
+    // `n run <script>` carries the script name in the second position
+    let position = if args.first().is_some_and(|arg| arg == "run") {
+        1
+    } else {
+        0
+    };
+
+    let Some(name) = args.get(position) else {
+        return Ok(args.to_vec());
+    };
+
+    let is_reserved = name.starts_with('-')
+        || (position == 0 && (expand_alias(name) != name || name == "help"))
+        || (position == 0 && is_known_command(manager, name, config))
+        || scripts.contains(name);
+
+    if is_reserved {
+        return Ok(args.to_vec());
+    }
+
+    let similar = find_similar_commands(name, scripts);
+
+    let similar = match (similar.as_slice(), terminal) {
+        ([_, _, ..], Some(input)) => {
+            eprintln!("n: no script `{}`, it could be any of these", name);
+            Some(picker::pick_script_from(&similar, input)?)
+        }
+        // Scripts that only differ in case or separators can't be told apart by a typo
+        ([_, _, ..], None) if has_normalized_twins(&similar) => {
+            log::verbose(format_args!(
+                "`{}` is ambiguous between scripts that only differ in case or separators",
+                name
+            ));
+            None
+        }
+        ([first, _, ..], None) => match config.on_ambiguous {
+            OnAmbiguous::First => Some(first.clone()),
+            OnAmbiguous::Decline => {
+                log::verbose(format_args!(
+                    "`{}` is as close to `{}` as to other scripts, not autocorrecting it",
+                    name, first
+                ));
+                None
+            }
+            OnAmbiguous::Fail => {
+                return Err(Error::Other(format!(
+                    "no script `{}`, it could be any of {}",
+                    name,
+                    similar.join(", ")
+                )));
+            }
+        },
+        _ => similar.into_iter().next(),
+    };
+
+    log::trace(
+        "autocorrect_candidates",
+        vec![
+            ("input", name.as_str().into()),
+            (
+                "candidates",
+                json::Value::Array(
+                    scripts
+                        .iter()
+                        .map(|script| script.as_str().into())
+                        .collect(),
+                ),
+            ),
+            ("chosen", similar.clone().into()),
+        ],
+    );
+
+    match similar {
+        Some(script) => {
+            eprintln!("n: no script `{}`, running `{}` instead", name, script);
+
+            let mut corrected = args.to_vec();
+            corrected[position] = script;
+            Ok(corrected)
+        }
+        None => Ok(args.to_vec()),
+    }
+}
+
+// A fresh project's `init`. npm and yarn would ask a round of questions first, the
+// others don't ask any.
+pub fn init_command(manager: PackageManager, rest: &[String]) -> Vec<String> {
+    match manager {
+        PackageManager::Npm | PackageManager::Yarn if rest.is_empty() => {
+            vec!["init".to_string(), "-y".to_string()]
+        }
+        _ => prepend("init", rest),
+    }
+}
+
+// `n typecheck` becomes `npm run typecheck`. Only yarn runs any script by its bare name,
+// bun does too but would pick its own commands over scripts of the same name.
+pub fn run_exact_script(
+    manager: PackageManager,
+    args: &[String],
+    scripts: &[String],
+    config: &Config,
+) -> Vec<String> {
+    let Some(first) = args.first() else {
+        return Vec::new();
+    };
+
+    let is_bare_script = scripts.contains(first)
+        && expand_alias(first) == first
+        && !is_known_command(manager, first, config);
+
+    if is_bare_script {
+        prepend_run(manager, first, &args[1..])
+    } else {
+        args.to_vec()
+    }
+}
+
+// A first argument that is neither an alias, a native command nor a script (even after
+// autocorrect) is most likely a binary the user wants to run through dlx
+pub fn is_dlx_candidate(
+    manager: PackageManager,
+    args: &[String],
+    scripts: &[String],
+    config: &Config,
+) -> bool {
+    let Some(first) = args.first() else {
+        return false;
+    };
+
+    !first.starts_with('-')
+        && expand_alias(first) == first
+        && !is_known_command(manager, first, config)
+        && !scripts.contains(first)
+        && find_similar_command(first, scripts).is_none()
+}
+
+// Splits `run build,test` (or a bare `build,test`) into one invocation per script,
+// each carrying the remaining arguments. Anything without a comma is returned as is.
+pub fn split_script_list(args: &[String]) -> Vec<Vec<String>> {
+    let position = if args.first().is_some_and(|arg| arg == "run") {
+        1
+    } else {
+        0
+    };
+
+    let Some(list) = args.get(position) else {
+        return vec![args.to_vec()];
+    };
+
+    let names: Vec<&str> = list.split(',').collect();
+    if list.starts_with('-') || names.len() < 2 || names.iter().any(|name| name.is_empty()) {
+        return vec![args.to_vec()];
+    }
+
+    names
+        .into_iter()
+        .map(|name| {
+            let mut invocation = args.to_vec();
+            invocation[position] = name.to_string();
+            invocation
+        })
+        .collect()
+}
+
+fn trace_alias(alias: &str, command: &str) {
+    log::trace(
+        "alias_applied",
+        vec![("alias", alias.into()), ("command", command.into())],
+    );
+}
+
+pub fn expand_alias(command: &str) -> &str {
+    match command {
+        "i" => "install",
+        "a" => "add",
+        "b" => "build",
+        "d" => "dev",
+        "s" => "start",
+        "t" => "test",
+        "x" => "dlx",
+        other => other,
+    }
+}
+
+// `n help a` shows the manager's help for whatever `n a` would run
+fn patch_help_command(manager: PackageManager, rest: &[String]) -> Vec<String> {
+    let command = expand_alias(&rest[0]);
+
+    let translated = match manager {
+        PackageManager::Npm => patch_npm_command(command, &[]),
+        PackageManager::Yarn => patch_yarn_command(command, &[]),
+        PackageManager::Pnpm => patch_pnpm_command(command, &[]),
+        PackageManager::Bun => patch_bun_command(command, &[]),
+        PackageManager::Deno => patch_deno_command(command, &[]),
+    };
+
+    let command = translated
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| command.to_string());
+
+    vec![command, "--help".to_string()]
+}
+
+// `n d` pointed at another script through `dev_alias`
+fn patch_dev_alias(
+    manager: PackageManager,
+    alias: &DevAlias,
+    rest: &[String],
+    config: &Config,
+) -> Vec<String> {
+    // bun needs `run` even for `test`, the others only for scripts shadowed by a command
+    let needs_run = alias.run.unwrap_or(match manager {
+        PackageManager::Bun => true,
+        _ => manager.run_prefix().is_some() && !is_known_command(manager, &alias.script, config),
+    });
+
+    if needs_run {
+        let verb = manager.run_prefix().unwrap_or("run");
+        prepend(verb, &prepend(&alias.script, rest))
+    } else {
+        prepend(&alias.script, rest)
+    }
+}
+
+fn patch_npm_command(command: &str, rest: &[String]) -> Vec<String> {
+    let patched = match command {
+        "install" | "add" => prepend("install", &normalize_dev_flag(PackageManager::Npm, rest)),
+        "build" | "dev" => prepend_run(PackageManager::Npm, command, rest),
+        // npm's dlx is `npx`, which is `npm exec` under the hood
+        "dlx" | "exec" => [vec!["exec".to_string(), "--".to_string()], rest.to_vec()].concat(),
+        _ => prepend(command, rest),
+    };
+
+    forward_port_flag(patched)
+}
+
+// npm reads `--port` as its own config unless it comes after `--`, the other managers
+// hand everything after the script name to the script
+fn forward_port_flag(patched: Vec<String>) -> Vec<String> {
+    let runs_script = patched
+        .first()
+        .is_some_and(|command| matches!(command.as_str(), "run" | "start" | "test" | "restart"));
+
+    if !runs_script {
+        return patched;
+    }
+
+    let (port, mut patched) = take_value_flag(&patched, "--port");
+    let Some(port) = port else {
+        return patched;
+    };
+
+    if !patched.iter().any(|arg| arg == "--") {
+        patched.push("--".to_string());
+    }
+    patched.push("--port".to_string());
+    patched.push(port);
+    patched
+}
+
+fn patch_yarn_command(command: &str, rest: &[String]) -> Vec<String> {
+    match command {
+        // yarn refuses `install <pkg>`, packages always go through `add`
+        "install" if rest.iter().any(|arg| !arg.starts_with('-')) => {
+            prepend("add", &normalize_dev_flag(PackageManager::Yarn, rest))
+        }
+        "add" => prepend("add", &normalize_dev_flag(PackageManager::Yarn, rest)),
+        "build" | "dev" => prepend_run(PackageManager::Yarn, command, rest),
+        "prune" => {
+            eprintln!("n: yarn has no prune, `yarn install` removes extraneous packages");
+            prepend("install", rest)
+        }
+        "dedupe" => {
+            eprintln!("n: `yarn dedupe` needs yarn 2 or later, yarn 1 has no equivalent");
+            prepend("dedupe", rest)
+        }
+        "rebuild" => {
+            eprintln!("n: `yarn rebuild` needs yarn 2 or later, yarn 1 has no equivalent");
+            prepend("rebuild", rest)
+        }
+        "patch" => {
+            eprintln!("n: `yarn patch` needs yarn 2 or later");
+            prepend("patch", rest)
+        }
+        // Without `--save` yarn only prints the patch, pnpm writes it right away
+        "patch-commit" if !rest.iter().any(|arg| arg == "-s" || arg == "--save") => {
+            eprintln!("n: `yarn patch-commit` needs yarn 2 or later");
+            [
+                vec!["patch-commit".to_string(), "--save".to_string()],
+                rest.to_vec(),
+            ]
+            .concat()
+        }
+        _ => prepend(command, rest),
+    }
+}
+
+fn patch_pnpm_command(command: &str, rest: &[String]) -> Vec<String> {
+    match command {
+        "install" if rest.iter().any(|arg| !arg.starts_with('-')) => {
+            prepend("add", &normalize_dev_flag(PackageManager::Pnpm, rest))
+        }
+        "add" => prepend("add", &normalize_dev_flag(PackageManager::Pnpm, rest)),
+        "build" | "dev" => prepend_run(PackageManager::Pnpm, command, rest),
+        _ => prepend(command, rest),
+    }
+}
+
+fn patch_bun_command(command: &str, rest: &[String]) -> Vec<String> {
+    match command {
+        "install" if rest.iter().any(|arg| !arg.starts_with('-')) => {
+            prepend("add", &normalize_dev_flag(PackageManager::Bun, rest))
+        }
+        "add" => prepend("add", &normalize_dev_flag(PackageManager::Bun, rest)),
+        // `bun x` prefers what's installed locally, `bun exec` runs a shell command instead
+        "dlx" | "exec" => prepend("x", rest),
+        "patch-commit" => [
+            vec!["patch".to_string(), "--commit".to_string()],
+            rest.to_vec(),
+        ]
+        .concat(),
+        "version" => {
+            eprintln!("n: bun has no `version`, trying `bun pm version` (bun 1.2.19 and later)");
+            [vec!["pm".to_string(), "version".to_string()], rest.to_vec()].concat()
+        }
+        // Registry metadata lives under `bun pm`
+        "info" => [vec!["pm".to_string(), "view".to_string()], rest.to_vec()].concat(),
+        "prune" => {
+            eprintln!("n: bun has no prune, `bun install` removes extraneous packages");
+            prepend("install", rest)
+        }
+        "dedupe" => {
+            eprintln!("n: bun has no dedupe, `bun install` already deduplicates what it can");
+            prepend("install", rest)
+        }
+        // A forced install is the closest bun gets, it can't be limited to some packages
+        "rebuild" => {
+            eprintln!("n: bun has no rebuild, reinstalling everything with `bun install --force`");
+            let flags: Vec<String> = rest
+                .iter()
+                .filter(|arg| arg.starts_with('-'))
+                .cloned()
+                .collect();
+            [vec!["install".to_string(), "--force".to_string()], flags].concat()
+        }
+        // `bun test` is bun's own test runner, the script needs an explicit `run`
+        command if SCRIPT_ALIASES.contains(&command) => {
+            prepend_run(PackageManager::Bun, command, rest)
+        }
+        _ => prepend(command, rest),
+    }
+}
+
+fn patch_deno_command(command: &str, rest: &[String]) -> Vec<String> {
+    match command {
+        "install" if rest.iter().any(|arg| !arg.starts_with('-')) => prepend(
+            "add",
+            &npm_specifiers(&normalize_dev_flag(PackageManager::Deno, rest)),
+        ),
+        "add" => prepend(
+            "add",
+            &npm_specifiers(&normalize_dev_flag(PackageManager::Deno, rest)),
+        ),
+        "ci" => [
+            vec!["install".to_string(), "--frozen".to_string()],
+            rest.to_vec(),
+        ]
+        .concat(),
+        // `deno uninstall` removes globally installed binaries
+        "rm" | "un" | "uninstall" => prepend("remove", rest),
+        // `deno run` runs files, package.json scripts go through `deno task`
+        "run" => prepend("task", rest),
+        "exec" => patch_deno_exec(rest, |bin| {
+            env::current_dir()
+                .ok()
+                .and_then(|dir| find_local_bin(&dir, bin))
+        }),
+        "dlx" => run_npm_package(rest),
+        // `deno test`, `deno serve` and friends are deno's own tools
+        command if SCRIPT_ALIASES.contains(&command) => {
+            prepend_run(PackageManager::Deno, command, rest)
+        }
+        _ => prepend(command, rest),
+    }
+}
+
+// deno wants `npm:lodash`, packages that already name a registry like `jsr:` are kept
+// deno has no exec, so the installed binary runs directly and npm only serves as a
+// fallback. Without `-A` deno asks before granting any permission.
+fn patch_deno_exec(rest: &[String], local_bin: impl Fn(&str) -> Option<PathBuf>) -> Vec<String> {
+    let Some(index) = rest.iter().position(|arg| !arg.starts_with('-')) else {
+        return run_npm_package(rest);
+    };
+
+    match local_bin(&rest[index]) {
+        Some(path) => {
+            let mut args = rest.to_vec();
+            args[index] = path.display().to_string();
+            prepend("run", &args)
+        }
+        None => {
+            eprintln!(
+                "n: no `{}` in node_modules/.bin, running it from npm",
+                rest[index]
+            );
+            run_npm_package(rest)
+        }
+    }
+}
+
+// The closest `node_modules/.bin/<bin>` at or above `dir`, hoisted workspaces keep it
+// at the root
+fn find_local_bin(dir: &Path, bin: &str) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|ancestor| ancestor.join("node_modules").join(".bin").join(bin))
+        .find(|path| path.is_file())
+}
+
+// Only the package gets `npm:`, the rest are its arguments
+fn run_npm_package(rest: &[String]) -> Vec<String> {
+    let mut args = rest.to_vec();
+    if let Some(package) = args.iter_mut().find(|arg| !arg.starts_with('-')) {
+        *package = npm_specifiers(std::slice::from_ref(package)).remove(0);
+    }
+    prepend("run", &args)
+}
+
+fn npm_specifiers(args: &[String]) -> Vec<String> {
+    args.iter()
+        .map(|arg| {
+            if arg.starts_with('-') || arg.contains(':') {
+                arg.clone()
+            } else {
+                format!("npm:{}", arg)
+            }
+        })
+        .collect()
+}
+
+// Replaces any dev dependency flag with the one the manager understands
+fn normalize_dev_flag(manager: PackageManager, args: &[String]) -> Vec<String> {
+    if !args.iter().any(|arg| DEV_FLAGS.contains(&arg.as_str())) {
+        return args.to_vec();
+    }
+
+    let mut patched: Vec<String> = args
+        .iter()
+        .filter(|arg| !DEV_FLAGS.contains(&arg.as_str()))
+        .cloned()
+        .collect();
+
+    patched.push(manager.dev_flag().to_string());
+    patched
+}
+
+fn normalize_peer_flag(manager: PackageManager, args: &[String]) -> Vec<String> {
+    let (peer, mut args) = take_flag(args, "--peer");
+    if !peer {
+        return args;
+    }
+
+    match manager.peer_flag() {
+        Some(flag) => args.push(flag.to_string()),
+        None => eprintln!(
+            "n: {} can't save peer dependencies, adding the packages as regular dependencies",
+            manager
+        ),
+    }
+    args
+}
+
+// Turns `add ui --workspace` into `add ui@workspace:*` for the managers that speak the
+// workspace protocol. On npm `--workspace <name>` picks the package to install into, so
+// only a trailing `--workspace` without a name is taken.
+fn normalize_workspace_protocol(manager: PackageManager, args: &[String]) -> Vec<String> {
+    if manager == PackageManager::Npm && args.last().is_none_or(|arg| arg != "--workspace") {
+        return args.to_vec();
+    }
+
+    let (workspace, args) = take_flag(args, "--workspace");
+    if !workspace {
+        return args;
+    }
+
+    match manager {
+        PackageManager::Pnpm | PackageManager::Yarn => args
+            .into_iter()
+            .map(|arg| {
+                if arg.starts_with('-') || has_version_spec(&arg) {
+                    arg
+                } else {
+                    format!("{}@workspace:*", arg)
+                }
+            })
+            .collect(),
+        PackageManager::Npm | PackageManager::Bun | PackageManager::Deno => {
+            eprintln!(
+                "n: {} doesn't support the workspace: protocol, adding the packages as regular dependencies",
+                manager
+            );
+            args
+        }
+    }
+}
+
+// `lodash@4` and `@scope/pkg@1` carry a version, `@scope/pkg` does not
+fn has_version_spec(package: &str) -> bool {
+    package.trim_start_matches('@').contains('@')
+}
+
+// Removes every occurrence of `flag` before a `--` separator and reports whether it was present
+pub fn take_flag(args: &[String], flag: &str) -> (bool, Vec<String>) {
+    let end = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+
+    let found = args[..end].iter().any(|arg| arg == flag);
+    let remaining = args[..end]
+        .iter()
+        .filter(|arg| *arg != flag)
+        .chain(&args[end..])
+        .cloned()
+        .collect();
+
+    (found, remaining)
+}
+
+// Bare numbers are milliseconds like the managers take them, `60s` or `2m` work too
+fn push_network_timeout(manager: PackageManager, timeout: &str, patched: &mut Vec<String>) {
+    let millis = if timeout.chars().all(|c| c.is_ascii_digit()) {
+        timeout.parse().ok()
+    } else {
+        parse_duration(timeout)
+            .ok()
+            .map(|duration| duration.as_millis())
+    };
+
+    let Some(millis) = millis else {
+        eprintln!("n: invalid --network-timeout `{}`, leaving it out", timeout);
+        return;
+    };
+
+    match manager.network_timeout_flag() {
+        Some(flag) => {
+            if manager == PackageManager::Yarn {
+                eprintln!("n: yarn 2 or later takes the timeout as `httpTimeout` in .yarnrc.yml");
+            }
+            patched.push(flag.to_string());
+            patched.push(millis.to_string());
+        }
+        None => eprintln!(
+            "n: {} has no network timeout flag, installing without one",
+            manager
+        ),
+    }
+}
+
+// Any spelling of "skip devDependencies", replaced with the manager's own flag later
+fn take_production_flags(args: &[String]) -> (bool, Vec<String>) {
+    let (prod, args) = take_flag(args, "--prod");
+    let (production, args) = take_flag(&args, "--production");
+    let (omit_dev, args) = take_flag(&args, "--omit=dev");
+
+    (prod || production || omit_dev, args)
+}
+
+// CI=true is the convention, the rest covers providers that don't set it
+fn is_ci() -> bool {
+    ci_from(&|name| env::var(name).ok())
+}
+
+fn ci_from(var: &dyn Fn(&str) -> Option<String>) -> bool {
+    let ci = var("CI").is_some_and(|ci| !matches!(ci.as_str(), "" | "0" | "false"));
+    ci || CI_VARIABLES.iter().any(|name| var(name).is_some())
+}
+
+// Removes `--flag value` or `--flag=value` before `--` and returns the value
+pub fn take_value_flag(args: &[String], flag: &str) -> (Option<String>, Vec<String>) {
+    let end = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+
+    let mut value = None;
+    let mut remaining = Vec::new();
+    let mut iter = args[..end].iter();
+
+    while let Some(arg) = iter.next() {
+        if arg == flag {
+            value = iter.next().cloned();
+        } else if let Some(inline) = arg
+            .strip_prefix(flag)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            value = Some(inline.to_string());
+        } else {
+            remaining.push(arg.clone());
+        }
+    }
+
+    remaining.extend_from_slice(&args[end..]);
+    (value, remaining)
+}
+
+fn prepend(command: &str, rest: &[String]) -> Vec<String> {
+    let mut patched = vec![command.to_string()];
+    patched.extend(rest.iter().cloned());
+    patched
+}
+
+// What happens to `--hot` on managers other than bun
+fn hot_flag_warning(manager: PackageManager) -> String {
+    match manager {
+        PackageManager::Npm => {
+            "n: --hot is a bun runtime flag, npm can't pass it on, dropping it".to_string()
+        }
+        manager => format!("n: --hot is a bun runtime flag, leaving it to {}", manager),
+    }
+}
+
+fn prepend_run(manager: PackageManager, script: &str, rest: &[String]) -> Vec<String> {
+    let patched = prepend(script, rest);
+
+    match manager.run_prefix() {
+        Some(verb) => prepend(verb, &patched),
+        None => patched,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{isolate_env, TempDir};
+
+    const MANAGERS: [PackageManager; 5] = [
+        PackageManager::Npm,
+        PackageManager::Yarn,
+        PackageManager::Pnpm,
+        PackageManager::Bun,
+        PackageManager::Deno,
+    ];
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn patch(manager: PackageManager, line: &str) -> String {
+        isolate_env();
+        patch_commands(manager, &args(line), &Config::default()).join(" ")
+    }
+
+    // `line` translated for every manager, in the order of MANAGERS
+    fn patch_all(line: &str) -> Vec<String> {
+        MANAGERS
+            .iter()
+            .map(|&manager| patch(manager, line))
+            .collect()
+    }
+
+    #[test]
+    fn dev_flag_per_manager() {
+        assert_eq!(
+            patch_all("a -D typescript"),
+            [
+                "install typescript -D",
+                "add typescript -D",
+                "add typescript -D",
+                "add typescript -d",
+                "add npm:typescript -D",
+            ]
+        );
+    }
+
+    #[test]
+    fn any_dev_flag_spelling_is_normalized() {
+        for flag in DEV_FLAGS {
+            let line = format!("i typescript {}", flag);
+            assert_eq!(patch(PackageManager::Yarn, &line), "add typescript -D");
+            assert_eq!(patch(PackageManager::Bun, &line), "add typescript -d");
+        }
+    }
+
+    #[test]
+    fn script_list_expands_in_order() {
+        assert_eq!(
+            split_script_list(&args("run build,test --silent")),
+            [args("run build --silent"), args("run test --silent")]
+        );
+        assert_eq!(
+            split_script_list(&args("build,test")),
+            [args("build"), args("test")]
+        );
+    }
+
+    #[test]
+    fn single_script_is_unaffected() {
+        assert_eq!(split_script_list(&args("run build")), [args("run build")]);
+        assert_eq!(split_script_list(&args("build,")), [args("build,")]);
+        assert_eq!(
+            split_script_list(&args("add --filter=a,b")),
+            [args("add --filter=a,b")]
+        );
+    }
+
+    #[test]
+    fn bun_runtime_flag_only_on_bun() {
+        assert_eq!(
+            patch_all("dev --bun-runtime"),
+            ["run dev", "dev", "run dev", "--bun run dev", "task dev"]
+        );
+    }
+
+    #[test]
+    fn unknown_tokens_are_dlx_candidates() {
+        let scripts = args("build typecheck");
+        let is_candidate = |line: &str| {
+            is_dlx_candidate(
+                PackageManager::Npm,
+                &args(line),
+                &scripts,
+                &Config::default(),
+            )
+        };
+
+        assert!(is_candidate("cowsay hello"));
+        assert!(is_candidate("create-vite my-app"));
+
+        // Aliases, native commands, scripts and near misses of scripts are not
+        assert!(!is_candidate("b"));
+        assert!(!is_candidate("install"));
+        assert!(!is_candidate("typecheck"));
+        assert!(!is_candidate("typechek"));
+        assert!(!is_candidate("--version"));
+        assert!(!is_candidate(""));
+    }
+
+    #[test]
+    fn ignore_scripts_is_added_to_installs_when_configured() {
+        let config = Config {
+            always_ignore_scripts: true,
+            ..Config::default()
+        };
+        let patch = |manager, line| patch_commands(manager, &args(line), &config).join(" ");
+
+        assert_eq!(
+            patch(PackageManager::Pnpm, "add lodash"),
+            "add lodash --ignore-scripts"
+        );
+        assert_eq!(
+            patch(PackageManager::Npm, "i lodash"),
+            "install lodash --ignore-scripts"
+        );
+        assert_eq!(patch(PackageManager::Npm, "run build"), "run build");
+    }
+
+    #[test]
+    fn explicit_ignore_scripts_passes_through_once() {
+        let config = Config {
+            always_ignore_scripts: true,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            patch_commands(
+                PackageManager::Yarn,
+                &args("add lodash --ignore-scripts"),
+                &config
+            ),
+            args("add lodash --ignore-scripts")
+        );
+        assert_eq!(
+            patch(PackageManager::Yarn, "add lodash --ignore-scripts"),
+            "add lodash --ignore-scripts"
+        );
+    }
+
+    #[test]
+    fn workspace_protocol_per_manager() {
+        assert_eq!(
+            patch_all("add ui --workspace"),
+            [
+                "install ui",
+                "add ui@workspace:*",
+                "add ui@workspace:*",
+                "add ui",
+                "add npm:ui"
+            ]
+        );
+        // npm's own `--workspace` names the package to install into
+        for line in [
+            "i lodash --workspace packages/app",
+            "i lodash --workspace=packages/app",
+        ] {
+            let expected = line.replacen("i ", "install ", 1);
+            assert_eq!(patch(PackageManager::Npm, line), expected);
+        }
+    }
+
+    #[test]
+    fn workspace_protocol_keeps_explicit_versions() {
+        assert_eq!(
+            patch(PackageManager::Pnpm, "add ui@1.0.0 --workspace"),
+            "add ui@1.0.0"
+        );
+    }
+
+    #[test]
+    fn peer_flag_per_manager() {
+        assert_eq!(
+            patch_all("add react --peer"),
+            [
+                "install react --save-peer",
+                "add react --peer",
+                "add react --save-peer",
+                "add react --peer",
+                "add npm:react"
+            ]
+        );
+    }
+
+    #[test]
+    fn help_for_an_alias_per_manager() {
+        assert_eq!(
+            patch_all("help a"),
+            [
+                "install --help",
+                "add --help",
+                "add --help",
+                "add --help",
+                "add --help"
+            ]
+        );
+        assert_eq!(patch(PackageManager::Yarn, "help"), "help");
+    }
+
+    #[test]
+    fn prune_per_manager() {
+        assert_eq!(
+            patch_all("prune --production"),
+            [
+                "prune --production",
+                "install --production",
+                "prune --production",
+                "install --production",
+                "prune --production"
+            ]
+        );
+        assert!(is_known_command(
+            PackageManager::Bun,
+            "prune",
+            &Config::default()
+        ));
+    }
+
+    #[test]
+    fn registry_per_manager() {
+        assert_eq!(
+            patch_all("i lodash --registry https://example/"),
+            [
+                "install lodash --registry https://example/",
+                "add lodash --registry https://example/",
+                "add lodash --registry https://example/",
+                "add lodash --registry https://example/",
+                "add npm:lodash --registry https://example/"
+            ]
+        );
+    }
+
+    #[test]
+    fn registry_value_is_not_taken_for_a_package() {
+        assert_eq!(
+            patch(
+                PackageManager::Deno,
+                "add --registry=https://example/ lodash"
+            ),
+            "add npm:lodash --registry https://example/"
+        );
+    }
+
+    fn dev_alias(script: &str, run: Option<bool>) -> Config {
+        Config {
+            dev_alias: Some(DevAlias {
+                script: script.to_string(),
+                run,
+            }),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn reconfigured_d_alias_on_npm_and_yarn() {
+        let config = dev_alias("serve", None);
+        let patch = |manager| patch_commands(manager, &args("d --port 3000"), &config).join(" ");
+
+        assert_eq!(patch(PackageManager::Npm), "run serve --port 3000");
+        assert_eq!(patch(PackageManager::Yarn), "serve --port 3000");
+    }
+
+    #[test]
+    fn d_alias_run_setting_wins_over_the_guess() {
+        let config = dev_alias("start", Some(true));
+
+        assert_eq!(
+            patch_commands(PackageManager::Npm, &args("d"), &config),
+            args("run start")
+        );
+        assert_eq!(
+            patch_commands(PackageManager::Yarn, &args("d"), &config),
+            args("run start")
+        );
+        assert_eq!(
+            patch_commands(PackageManager::Npm, &args("d"), &dev_alias("start", None)),
+            args("start")
+        );
+    }
+
+    #[test]
+    fn info_per_manager() {
+        assert_eq!(
+            patch_all("info react"),
+            [
+                "info react",
+                "info react",
+                "info react",
+                "pm view react",
+                "info react"
+            ]
+        );
+        assert!(is_known_command(
+            PackageManager::Pnpm,
+            "info",
+            &Config::default()
+        ));
+    }
+
+    #[test]
+    fn production_install_per_manager() {
+        assert_eq!(
+            patch_all("install --prod"),
+            [
+                "install --omit=dev",
+                "install --production",
+                "install --prod",
+                "install --production",
+                "install"
+            ]
+        );
+        assert_eq!(
+            patch(PackageManager::Pnpm, "i --production"),
+            patch(PackageManager::Pnpm, "i --omit=dev")
+        );
+    }
+
+    #[test]
+    fn dedupe_per_manager() {
+        assert_eq!(
+            patch_all("dedupe"),
+            ["dedupe", "dedupe", "dedupe", "install", "dedupe"]
+        );
+    }
+
+    #[test]
+    fn fund_passes_through_despite_a_similar_script() {
+        let scripts = args("funds fun");
+
+        for manager in MANAGERS {
+            let corrected =
+                try_autocorrect_script(manager, &args("fund --json"), &scripts, &Config::default());
+            assert_eq!(corrected, Ok(args("fund --json")));
+        }
+        assert_eq!(patch(PackageManager::Npm, "fund --json"), "fund --json");
+    }
+
+    #[test]
+    fn rebuild_per_manager() {
+        let expected = [
+            (PackageManager::Npm, "rebuild", "rebuild sharp"),
+            (PackageManager::Yarn, "rebuild", "rebuild sharp"),
+            (PackageManager::Pnpm, "rebuild", "rebuild sharp"),
+            (PackageManager::Bun, "install --force", "install --force"),
+            (PackageManager::Deno, "rebuild", "rebuild sharp"),
+        ];
+
+        for (manager, all, single) in expected {
+            assert_eq!(patch(manager, "rebuild"), all, "{}", manager);
+            assert_eq!(patch(manager, "rebuild sharp"), single, "{}", manager);
+        }
+    }
+
+    #[test]
+    fn version_bump_per_manager() {
+        let expected = [
+            (PackageManager::Npm, "version minor"),
+            (PackageManager::Yarn, "version minor"),
+            (PackageManager::Pnpm, "version minor"),
+            (PackageManager::Bun, "pm version minor"),
+            (PackageManager::Deno, "version minor"),
+        ];
+
+        for (manager, command) in expected {
+            assert_eq!(patch(manager, "version minor"), command, "{}", manager);
+        }
+    }
+
+    #[test]
+    fn exact_script_name_runs_the_script_per_manager() {
+        let scripts = args("typecheck build");
+        let expected = [
+            (PackageManager::Npm, "run typecheck --pretty"),
+            (PackageManager::Yarn, "typecheck --pretty"),
+            (PackageManager::Pnpm, "run typecheck --pretty"),
+            (PackageManager::Bun, "run typecheck --pretty"),
+            (PackageManager::Deno, "task typecheck --pretty"),
+        ];
+
+        for (manager, command) in expected {
+            let patched = run_exact_script(
+                manager,
+                &args("typecheck --pretty"),
+                &scripts,
+                &Config::default(),
+            );
+            assert_eq!(patched.join(" "), command, "{}", manager);
+        }
+        let untouched = run_exact_script(
+            PackageManager::Npm,
+            &args("lint"),
+            &scripts,
+            &Config::default(),
+        );
+        assert_eq!(untouched, args("lint"));
+    }
+
+    #[test]
+    fn port_reaches_the_start_script_per_manager() {
+        assert_eq!(
+            patch_all("s --port 4000"),
+            [
+                "start -- --port 4000",
+                "start --port 4000",
+                "start --port 4000",
+                "run start --port 4000",
+                "task start --port 4000",
+            ]
+        );
+        assert_eq!(
+            patch(PackageManager::Npm, "s --port=4000 -- --open"),
+            "start -- --open --port 4000"
+        );
+    }
+
+    #[test]
+    fn ci_freezes_plain_installs_unless_no_frozen() {
+        let install = |line: &str, ci| {
+            MANAGERS
+                .iter()
+                .map(|&manager| patch_commands_with(manager, &args(line), &Config::default(), ci))
+                .map(|patched| patched.join(" "))
+                .collect::<Vec<_>>()
+        };
+        let frozen = [
+            "ci",
+            "install --frozen-lockfile",
+            "install --frozen-lockfile",
+            "install --frozen-lockfile",
+            "install --frozen",
+        ];
+        let plain = ["install"; 5];
+
+        assert_eq!(install("install", true), frozen);
+        assert_eq!(install("install", false), plain);
+        assert_eq!(install("install --no-frozen", true), plain);
+        assert_eq!(install("install --frozen", false), frozen);
+    }
+
+    #[test]
+    fn ci_variables_are_recognized() {
+        let ci = |vars: &[(&str, &str)]| {
+            ci_from(&|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+
+        assert!(ci(&[("CI", "true")]));
+        assert!(ci(&[("GITHUB_ACTIONS", "true")]));
+        assert!(!ci(&[("CI", "false")]));
+        assert!(!ci(&[]));
+    }
+
+    #[test]
+    fn config_known_commands_are_not_autocorrected() {
+        let scripts = args("deploy:prod sbom");
+        let config = Config {
+            extra_known_commands: [("pnpm".to_string(), args("deploy sbom-export"))]
+                .into_iter()
+                .collect(),
+            ..Config::default()
+        };
+
+        let corrected =
+            try_autocorrect_script(PackageManager::Pnpm, &args("deploy out"), &scripts, &config);
+        assert_eq!(corrected, Ok(args("deploy out")));
+
+        // Only for the manager it was added to
+        let corrected =
+            try_autocorrect_script(PackageManager::Yarn, &args("deploy"), &scripts, &config);
+        assert_eq!(corrected, Ok(args("deploy:prod")));
+    }
+
+    #[test]
+    fn patch_per_manager() {
+        assert_eq!(patch_all("patch lodash"), ["patch lodash"; 5]);
+        assert_eq!(
+            patch_all("patch-commit patches/lodash")[1..4],
+            [
+                "patch-commit --save patches/lodash",
+                "patch-commit patches/lodash",
+                "patch --commit patches/lodash",
+            ]
+        );
+    }
+
+    #[test]
+    fn script_aliases_use_the_run_prefix() {
+        for manager in MANAGERS {
+            let expected = match manager.run_prefix() {
+                Some(verb) => format!("{} build --minify", verb),
+                None => "build --minify".to_string(),
+            };
+            assert_eq!(patch(manager, "b --minify"), expected, "{}", manager);
+        }
+    }
+
+    #[test]
+    fn hot_goes_in_front_of_run_on_bun_only() {
+        assert_eq!(
+            patch_all("dev --hot --port 3000"),
+            [
+                "run dev -- --port 3000",
+                "dev --hot --port 3000",
+                "run dev --hot --port 3000",
+                "--hot run dev --port 3000",
+                "task dev --hot --port 3000",
+            ]
+        );
+        assert_eq!(
+            patch(PackageManager::Bun, "d --watch --hot"),
+            "--hot --watch run dev"
+        );
+        for manager in [
+            PackageManager::Yarn,
+            PackageManager::Pnpm,
+            PackageManager::Deno,
+        ] {
+            assert_eq!(
+                hot_flag_warning(manager),
+                format!("n: --hot is a bun runtime flag, leaving it to {}", manager)
+            );
+        }
+        assert!(hot_flag_warning(PackageManager::Npm).ends_with("dropping it"));
+    }
+
+    #[test]
+    fn hot_and_watch_stay_on_commands_that_are_not_scripts() {
+        assert_eq!(
+            patch(PackageManager::Bun, "exec vite --hot"),
+            "x vite --hot"
+        );
+        assert_eq!(
+            patch(PackageManager::Bun, "x vite --watch"),
+            "x vite --watch"
+        );
+        assert_eq!(
+            patch(PackageManager::Npm, "exec vite --hot"),
+            "exec -- vite --hot"
+        );
+        for manager in MANAGERS {
+            assert!(patch(manager, "ci --hot").ends_with("--hot"), "{}", manager);
+        }
+    }
+
+    #[test]
+    fn exec_per_manager() {
+        assert_eq!(
+            patch_all("exec eslint ."),
+            [
+                "exec -- eslint .",
+                "exec eslint .",
+                "exec eslint .",
+                "x eslint .",
+                "run npm:eslint .",
+            ]
+        );
+    }
+
+    #[test]
+    fn deno_translates_each_command() {
+        for (line, expected) in [
+            ("install", "install"),
+            ("i lodash -D", "add npm:lodash -D"),
+            ("add jsr:@std/path", "add jsr:@std/path"),
+            ("ci", "install --frozen"),
+            ("rm lodash", "remove lodash"),
+            ("uninstall lodash", "remove lodash"),
+            ("run lint --fix", "task lint --fix"),
+            ("b", "task build"),
+            ("t --filter api", "task test --filter api"),
+            ("dlx cowsay hi", "run npm:cowsay hi"),
+            ("dlx -A cowsay hi", "run -A npm:cowsay hi"),
+            ("x npm:cowsay@1 hi", "run npm:cowsay@1 hi"),
+            ("exec eslint .", "run npm:eslint ."),
+            ("fmt --check", "fmt --check"),
+        ] {
+            assert_eq!(patch(PackageManager::Deno, line), expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn deno_exec_prefers_the_installed_binary() {
+        let dir = TempDir::new();
+        let eslint = dir.write("node_modules/.bin/eslint", "");
+        let app = dir.write("packages/app/package.json", "{}");
+        let app = app.parent().unwrap();
+
+        assert_eq!(find_local_bin(app, "eslint"), Some(eslint.clone()));
+        assert_eq!(find_local_bin(app, "vite"), None);
+
+        let patched = patch_deno_exec(&args("-A eslint ."), |bin| find_local_bin(app, bin));
+        assert_eq!(patched, ["run", "-A", &eslint.display().to_string(), "."]);
+        assert_eq!(
+            patch_deno_exec(&args("vite build"), |_| None),
+            args("run npm:vite build")
+        );
+    }
+
+    #[test]
+    fn on_ambiguous_decides_ties_without_a_terminal() {
+        let scripts = args("lint:js lint:ts");
+        let correct = |on_ambiguous| {
+            let config = Config {
+                on_ambiguous,
+                ..Config::default()
+            };
+            autocorrect_script_with(
+                PackageManager::Npm,
+                &args("lint:xs"),
+                &scripts,
+                &config,
+                None,
+            )
+        };
+
+        assert_eq!(correct(OnAmbiguous::First), Ok(args("lint:js")));
+        assert_eq!(correct(OnAmbiguous::Decline), Ok(args("lint:xs")));
+        assert_eq!(
+            correct(OnAmbiguous::Fail),
+            Err(Error::Other(
+                "no script `lint:xs`, it could be any of lint:js, lint:ts".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn ties_on_a_terminal_go_to_the_picker() {
+        let correct = |scripts: &str, input: &str, answer: &str| {
+            let config = Config {
+                on_ambiguous: OnAmbiguous::Fail,
+                ..Config::default()
+            };
+            autocorrect_script_with(
+                PackageManager::Npm,
+                &args(input),
+                &args(scripts),
+                &config,
+                Some(&mut answer.as_bytes()),
+            )
+        };
+
+        assert_eq!(
+            correct("lint:js lint:ts", "lint:xs", "2\n"),
+            Ok(args("lint:ts"))
+        );
+        assert_eq!(
+            correct("type-check typecheck lint", "typechek", "typecheck\n"),
+            Ok(args("typecheck"))
+        );
+        assert_eq!(
+            correct("lint:js lint:ts", "lint:xs", ""),
+            Err(Error::Aborted)
+        );
+        // A single close script needs no pick
+        assert_eq!(correct("lint build", "lnt", ""), Ok(args("lint")));
+    }
+
+    #[test]
+    fn normalized_twins_are_left_alone_without_a_terminal() {
+        let corrected = autocorrect_script_with(
+            PackageManager::Npm,
+            &args("typechek"),
+            &args("type-check typecheck"),
+            &Config::default(),
+            None,
+        );
+
+        assert_eq!(corrected, Ok(args("typechek")));
+    }
+
+    #[test]
+    fn lockfile_only_per_manager() {
+        let expected = [
+            "install --package-lock-only",
+            "install --mode=update-lockfile",
+            "install --lockfile-only",
+            "install --lockfile-only",
+            "install",
+        ];
+
+        assert_eq!(patch_all("install --lockfile-only"), expected);
+        assert_eq!(patch_all("i --package-lock-only"), expected);
+        // Freezing the lockfile would make the manager refuse to write it
+        for (manager, expected) in MANAGERS.into_iter().zip(expected) {
+            let patched = patch_commands_with(
+                manager,
+                &args("install --lockfile-only"),
+                &Config::default(),
+                true,
+            );
+            assert_eq!(patched.join(" "), expected, "{}", manager);
+        }
+    }
+
+    #[test]
+    fn network_timeout_per_manager() {
+        let expected = [
+            "install --fetch-timeout 60000",
+            "install --network-timeout 60000",
+            "install --fetch-timeout 60000",
+            "install",
+            "install",
+        ];
+
+        assert_eq!(patch_all("install --network-timeout 60000"), expected);
+        assert_eq!(patch_all("i --network-timeout=1m"), expected);
+    }
+
+    #[test]
+    fn yarn_force_run_adds_run_to_scripts_only() {
+        let forced = Config {
+            yarn_force_run: true,
+            ..Config::default()
+        };
+        let yarn = |line, config: &Config| {
+            patch_commands(PackageManager::Yarn, &args(line), config).join(" ")
+        };
+
+        assert_eq!(yarn("dev --open", &Config::default()), "dev --open");
+        assert_eq!(yarn("d --open", &Config::default()), "dev --open");
+        assert_eq!(yarn("dev --open", &forced), "run dev --open");
+        assert_eq!(yarn("d --open", &forced), "run dev --open");
+        assert_eq!(yarn("run dev", &forced), "run dev");
+        assert_eq!(yarn("add lodash", &forced), "add lodash");
+    }
+}
@@ -0,0 +1,879 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::log;
+use crate::package_manager::PackageManager;
+use crate::toml::{self, Table, Value};
+
+const PROJECT_CONFIG: &str = ".nrc.toml";
+// The older `key=value` format, read below `.nrc.toml`
+const LEGACY_CONFIG: &str = ".nrc";
+
+// The newest `behavior_version` this n knows, what projects get unless they pin one
+pub const LATEST_BEHAVIOR: i64 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    // Keeps documented behavior of an older n, see LATEST_BEHAVIOR
+    pub behavior_version: i64,
+    pub install_flags: Vec<String>,
+    pub autocorrect: bool,
+    // What autocorrect does without a terminal when several scripts are equally close
+    pub on_ambiguous: OnAmbiguous,
+    // Extra lockfile names and the manager they stand for, checked before the built-in ones
+    pub lockfiles: BTreeMap<String, PackageManager>,
+    // Let `.tool-versions` win over lockfiles found in the same directory, on by default
+    pub prefer_tool_versions: bool,
+    // Pick deno over an npm-family lockfile in the same directory
+    pub prefer_deno: bool,
+    // Run yarn scripts as `yarn run <script>` instead of `yarn <script>`
+    pub yarn_force_run: bool,
+    // Add `--ignore-scripts` to every install
+    pub always_ignore_scripts: bool,
+    // Also offer recipes from a justfile or Taskfile next to package.json
+    pub task_files: bool,
+    // Commands that ask for confirmation before running
+    pub confirm: Vec<String>,
+    // Commands n refuses to run
+    pub deny: Vec<String>,
+    // Directory names detection walks through without looking inside
+    pub ignore_dirs: Vec<String>,
+    // Commands that run in the detected root instead of the current directory
+    pub root_commands: Vec<String>,
+    // Executables to spawn instead of the manager itself, keyed by manager name
+    pub binaries: BTreeMap<String, String>,
+    // Subcommands to treat as native on top of the built-in lists, keyed by manager name
+    pub extra_known_commands: BTreeMap<String, Vec<String>>,
+    // Set `NODE_ENV` for the scripts in `node_env`
+    pub set_node_env: bool,
+    // Script name to `NODE_ENV` value
+    pub node_env: BTreeMap<String, String>,
+    // What `--bail-on-warning` looks for in a script's output, ignoring case
+    pub warning_marker: String,
+    // The registry `n doctor` expects the project's .npmrc to point at
+    pub registry: Option<String>,
+    // Where `n d` goes instead of the `dev` script
+    pub dev_alias: Option<DevAlias>,
+    // Where each top-level setting was last set, settings missing here are defaults
+    pub sources: BTreeMap<String, Source>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DevAlias {
+    pub script: String,
+    // Whether the script needs `run` in front, guessed per manager when unset
+    pub run: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnAmbiguous {
+    // Go with the first of them in manifest order
+    First,
+    // Leave the input alone, the manager reports the missing script
+    Decline,
+    // Stop and list them
+    Fail,
+}
+
+impl OnAmbiguous {
+    fn from_name(name: &str) -> Option<OnAmbiguous> {
+        match name {
+            "first" => Some(OnAmbiguous::First),
+            "none" => Some(OnAmbiguous::Decline),
+            "error" => Some(OnAmbiguous::Fail),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            OnAmbiguous::First => "first",
+            OnAmbiguous::Decline => "none",
+            OnAmbiguous::Fail => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Default,
+    Global(PathBuf),
+    Project(PathBuf),
+    Profile(String),
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            behavior_version: LATEST_BEHAVIOR,
+            install_flags: Vec::new(),
+            autocorrect: true,
+            on_ambiguous: OnAmbiguous::First,
+            lockfiles: BTreeMap::new(),
+            prefer_tool_versions: true,
+            prefer_deno: false,
+            yarn_force_run: false,
+            always_ignore_scripts: false,
+            task_files: false,
+            confirm: Vec::new(),
+            deny: Vec::new(),
+            ignore_dirs: Vec::new(),
+            root_commands: Vec::new(),
+            binaries: BTreeMap::new(),
+            extra_known_commands: BTreeMap::new(),
+            set_node_env: false,
+            node_env: BTreeMap::from([
+                ("build".to_string(), "production".to_string()),
+                ("dev".to_string(), "development".to_string()),
+            ]),
+            warning_marker: "warning:".to_string(),
+            registry: None,
+            dev_alias: None,
+            sources: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    // Merges the global config, the project config and finally the selected profile
+    pub fn load(dir: &Path, profile: Option<&str>) -> Result<Config, String> {
+        Config::load_with_global(global_config_path(), dir, profile)
+    }
+
+    fn load_with_global(
+        global: Option<PathBuf>,
+        dir: &Path,
+        profile: Option<&str>,
+    ) -> Result<Config, String> {
+        let mut table = Table::new();
+        let mut sources = BTreeMap::new();
+
+        if let Some(path) = global {
+            let global = read_table(&path)?;
+            record_sources(&mut sources, &global, Source::Global(path));
+            toml::merge(&mut table, &global);
+        }
+
+        let dir = find_project_config_dir(dir);
+
+        let path = dir.join(LEGACY_CONFIG);
+        let legacy = read_legacy_table(&path)?;
+        record_sources(&mut sources, &legacy, Source::Project(path));
+        toml::merge(&mut table, &legacy);
+
+        let path = dir.join(PROJECT_CONFIG);
+        let project = read_table(&path)?;
+        record_sources(&mut sources, &project, Source::Project(path));
+        toml::merge(&mut table, &project);
+
+        let profile = profile
+            .map(str::to_string)
+            .or_else(|| env::var("N_PROFILE").ok().filter(|name| !name.is_empty()));
+
+        if let Some(name) = profile {
+            let overlay = table
+                .get("profiles")
+                .and_then(|profiles| profiles.as_table())
+                .and_then(|profiles| profiles.get(&name))
+                .and_then(|profile| profile.as_table())
+                .cloned()
+                .ok_or_else(|| format!("unknown profile `{}`", name))?;
+
+            record_sources(&mut sources, &overlay, Source::Profile(name));
+            toml::merge(&mut table, &overlay);
+        }
+
+        let mut config = Config::from_table(&table)?;
+        config.sources = sources;
+        Ok(config)
+    }
+
+    pub fn from_table(table: &Table) -> Result<Config, String> {
+        let mut config = Config::default();
+
+        if let Some(value) = table.get("behavior_version") {
+            let Value::Integer(version) = value else {
+                return Err("`behavior_version` must be a number".to_string());
+            };
+
+            match known_behavior(*version) {
+                Ok(version) => config.behavior_version = version,
+                Err(warning) => eprintln!("n: {}", warning),
+            }
+        }
+        if let Some(value) = table.get("install_flags") {
+            config.install_flags = string_array(value, "install_flags")?;
+        }
+        if let Some(value) = table.get("autocorrect") {
+            config.autocorrect = bool_value(value, "autocorrect")?;
+        }
+        if let Some(value) = table.get("on_ambiguous") {
+            config.on_ambiguous = value
+                .as_str()
+                .and_then(OnAmbiguous::from_name)
+                .ok_or("`on_ambiguous` must be \"first\", \"none\" or \"error\"")?;
+        }
+        if let Some(value) = table.get("prefer_tool_versions") {
+            config.prefer_tool_versions = bool_value(value, "prefer_tool_versions")?;
+        }
+        if let Some(value) = table.get("prefer_deno") {
+            config.prefer_deno = bool_value(value, "prefer_deno")?;
+        }
+        if let Some(value) = table.get("yarn_force_run") {
+            config.yarn_force_run = bool_value(value, "yarn_force_run")?;
+        }
+        if let Some(value) = table.get("always_ignore_scripts") {
+            config.always_ignore_scripts = bool_value(value, "always_ignore_scripts")?;
+        }
+        if let Some(value) = table.get("task_files") {
+            config.task_files = bool_value(value, "task_files")?;
+        }
+        if let Some(value) = table.get("confirm") {
+            config.confirm = string_array(value, "confirm")?;
+        }
+        if let Some(value) = table.get("deny") {
+            config.deny = string_array(value, "deny")?;
+        }
+        if let Some(value) = table.get("ignore_dirs") {
+            config.ignore_dirs = string_array(value, "ignore_dirs")?;
+        }
+        if let Some(value) = table.get("root_commands") {
+            config.root_commands = string_array(value, "root_commands")?;
+        }
+        if let Some(value) = table.get("lockfiles") {
+            config.lockfiles = manager_map(value, "lockfiles")?;
+        }
+        if let Some(value) = table.get("binaries") {
+            config.binaries = binary_map(value, "binaries")?;
+        }
+        if let Some(value) = table.get("extra_known_commands") {
+            config.extra_known_commands = command_map(value, "extra_known_commands")?;
+        }
+        if let Some(value) = table.get("set_node_env") {
+            config.set_node_env = bool_value(value, "set_node_env")?;
+        }
+        if let Some(value) = table.get("node_env") {
+            config.node_env = string_map(value, "node_env")?;
+        }
+        if let Some(value) = table.get("warning_marker") {
+            let marker = value
+                .as_str()
+                .filter(|marker| !marker.is_empty())
+                .ok_or("`warning_marker` must be a non-empty string")?;
+            config.warning_marker = marker.to_string();
+        }
+        if let Some(value) = table.get("registry") {
+            let registry = value.as_str().ok_or("`registry` must be a string")?;
+            config.registry = Some(registry.to_string());
+        }
+        if let Some(value) = table.get("dev_alias") {
+            config.dev_alias = Some(dev_alias(value)?);
+        }
+
+        Ok(config)
+    }
+
+    pub fn to_table(&self) -> Table {
+        let mut table = Table::new();
+
+        table.insert(
+            "behavior_version".to_string(),
+            Value::Integer(self.behavior_version),
+        );
+        table.insert(
+            "install_flags".to_string(),
+            string_array_value(&self.install_flags),
+        );
+        table.insert("autocorrect".to_string(), Value::Boolean(self.autocorrect));
+        table.insert(
+            "on_ambiguous".to_string(),
+            Value::String(self.on_ambiguous.name().to_string()),
+        );
+        table.insert(
+            "prefer_tool_versions".to_string(),
+            Value::Boolean(self.prefer_tool_versions),
+        );
+        table.insert("prefer_deno".to_string(), Value::Boolean(self.prefer_deno));
+        table.insert(
+            "yarn_force_run".to_string(),
+            Value::Boolean(self.yarn_force_run),
+        );
+        table.insert(
+            "always_ignore_scripts".to_string(),
+            Value::Boolean(self.always_ignore_scripts),
+        );
+        table.insert("task_files".to_string(), Value::Boolean(self.task_files));
+        table.insert("confirm".to_string(), string_array_value(&self.confirm));
+        table.insert("deny".to_string(), string_array_value(&self.deny));
+        table.insert(
+            "ignore_dirs".to_string(),
+            string_array_value(&self.ignore_dirs),
+        );
+        table.insert(
+            "root_commands".to_string(),
+            string_array_value(&self.root_commands),
+        );
+        table.insert(
+            "lockfiles".to_string(),
+            Value::Table(
+                self.lockfiles
+                    .iter()
+                    .map(|(lockfile, manager)| {
+                        (lockfile.clone(), Value::String(manager.name().to_string()))
+                    })
+                    .collect(),
+            ),
+        );
+
+        table.insert(
+            "binaries".to_string(),
+            Value::Table(
+                self.binaries
+                    .iter()
+                    .map(|(manager, binary)| (manager.clone(), Value::String(binary.clone())))
+                    .collect(),
+            ),
+        );
+
+        table.insert(
+            "extra_known_commands".to_string(),
+            Value::Table(
+                self.extra_known_commands
+                    .iter()
+                    .map(|(manager, commands)| (manager.clone(), string_array_value(commands)))
+                    .collect(),
+            ),
+        );
+
+        table.insert(
+            "set_node_env".to_string(),
+            Value::Boolean(self.set_node_env),
+        );
+        table.insert(
+            "node_env".to_string(),
+            Value::Table(
+                self.node_env
+                    .iter()
+                    .map(|(script, value)| (script.clone(), Value::String(value.clone())))
+                    .collect(),
+            ),
+        );
+
+        table.insert(
+            "warning_marker".to_string(),
+            Value::String(self.warning_marker.clone()),
+        );
+
+        if let Some(registry) = &self.registry {
+            table.insert("registry".to_string(), Value::String(registry.clone()));
+        }
+
+        if let Some(alias) = &self.dev_alias {
+            let mut entry = Table::new();
+            entry.insert("script".to_string(), Value::String(alias.script.clone()));
+            if let Some(run) = alias.run {
+                entry.insert("run".to_string(), Value::Boolean(run));
+            }
+            table.insert("dev_alias".to_string(), Value::Table(entry));
+        }
+
+        table
+    }
+
+    // What to spawn for `manager`, a shim from `binaries` or the manager itself
+    pub fn binary(&self, manager: PackageManager) -> &str {
+        self.binaries
+            .get(manager.name())
+            .map(String::as_str)
+            .unwrap_or(manager.name())
+    }
+
+    // Renders the effective settings as TOML, annotating each line with its source
+    pub fn dump(&self) -> String {
+        self.to_table()
+            .iter()
+            .map(|(key, value)| {
+                let source = self.sources.get(key).unwrap_or(&Source::Default);
+                format!(
+                    "{} = {} # {}\n",
+                    toml::format_key(key),
+                    toml::format_value(value),
+                    source
+                )
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Global(path) => write!(f, "global ({})", path.display()),
+            Source::Project(path) => write!(f, "project ({})", path.display()),
+            Source::Profile(name) => write!(f, "profile `{}`", name),
+        }
+    }
+}
+
+// The closest directory at or above `dir` with a `.nrc.toml` or `.nrc`, `dir` if there is none
+fn find_project_config_dir(dir: &Path) -> &Path {
+    dir.ancestors()
+        .find(|ancestor| {
+            ancestor.join(PROJECT_CONFIG).is_file() || ancestor.join(LEGACY_CONFIG).is_file()
+        })
+        .unwrap_or(dir)
+}
+
+pub fn global_config_path() -> Option<PathBuf> {
+    // An empty or relative XDG_CONFIG_HOME is invalid per the spec and would land in the cwd
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join("n").join("config.toml"))
+}
+
+fn record_sources(sources: &mut BTreeMap<String, Source>, table: &Table, source: Source) {
+    for key in table.keys().filter(|key| *key != "profiles") {
+        sources.insert(key.clone(), source.clone());
+    }
+}
+
+// Made for a newer n, the latest behavior this one knows is the closest match
+fn known_behavior(version: i64) -> Result<i64, String> {
+    if (1..=LATEST_BEHAVIOR).contains(&version) {
+        Ok(version)
+    } else {
+        Err(format!(
+            "unknown behavior_version {}, this n knows 1 to {} and uses {}",
+            version, LATEST_BEHAVIOR, LATEST_BEHAVIOR
+        ))
+    }
+}
+
+fn read_table(path: &Path) -> Result<Table, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => toml::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(_) => Ok(Table::new()),
+    }
+}
+
+// Each `key=value` line is read like the TOML setting of the same name, values that
+// aren't valid TOML are taken as plain strings
+fn read_legacy_table(path: &Path) -> Result<Table, String> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Ok(Table::new());
+    };
+
+    // Every setting shows up in a dumped default config except the optional ones
+    let known = Config::default().to_table();
+
+    let mut table = Table::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!(
+                "{}: line {}: expected `key=value`",
+                path.display(),
+                number + 1
+            ));
+        };
+        let (key, value) = (key.trim(), value.trim());
+
+        if !known.contains_key(key) && !matches!(key, "dev_alias" | "registry") {
+            log::verbose(format_args!(
+                "{}: unknown setting `{}`",
+                path.display(),
+                key
+            ));
+            continue;
+        }
+
+        table.insert(key.to_string(), parse_loose_value(value));
+    }
+
+    Ok(table)
+}
+
+// A TOML value, or the text as a string when it isn't one
+fn parse_loose_value(value: &str) -> Value {
+    toml::parse(&format!("value = {}", value))
+        .ok()
+        .and_then(|mut parsed| parsed.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+// The value at a dotted `key` in the user config file
+pub fn get_user_setting(key: &str) -> Result<Value, String> {
+    let path = global_config_path().ok_or("can't find the user config directory")?;
+    get_setting(&path, key)
+}
+
+fn get_setting(path: &Path, key: &str) -> Result<Value, String> {
+    let table = read_table(path)?;
+
+    toml::get_path(&table, key)
+        .cloned()
+        .ok_or_else(|| format!("`{}` is not set in {}", key, path.display()))
+}
+
+// Sets a dotted `key` in the user config file, creating it if needed. The result has to
+// load, so a mistyped value never ends up in the file. The file is written back from the
+// parsed table, which has no room for comments, so those are lost.
+pub fn set_user_setting(key: &str, value: &str) -> Result<PathBuf, String> {
+    let path = global_config_path().ok_or("can't find the user config directory")?;
+    set_setting(&path, key, value)?;
+    Ok(path)
+}
+
+fn set_setting(path: &Path, key: &str, value: &str) -> Result<(), String> {
+    let mut table = read_table(path)?;
+    let has_comments = fs::read_to_string(path).is_ok_and(|contents| {
+        contents
+            .lines()
+            .any(|line| line.trim_start().starts_with('#'))
+    });
+
+    toml::set_path(&mut table, key, parse_loose_value(value))?;
+    Config::from_table(&table)?;
+
+    let write_error = |e| format!("failed to write {}: {}", path.display(), e);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(write_error)?;
+    }
+    fs::write(path, toml::to_string(&table)).map_err(write_error)?;
+
+    if has_comments {
+        eprintln!("n: comments in {} were not kept", path.display());
+    }
+    Ok(())
+}
+
+fn bool_value(value: &Value, key: &str) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("`{}` must be a boolean", key))
+}
+
+fn string_array(value: &Value, key: &str) -> Result<Vec<String>, String> {
+    let error = || format!("`{}` must be an array of strings", key);
+
+    match value {
+        Value::Array(values) => values
+            .iter()
+            .map(|value| value.as_str().map(str::to_string).ok_or_else(error))
+            .collect(),
+        _ => Err(error()),
+    }
+}
+
+fn string_array_value(values: &[String]) -> Value {
+    Value::Array(
+        values
+            .iter()
+            .map(|value| Value::String(value.clone()))
+            .collect(),
+    )
+}
+
+fn manager_map(value: &Value, key: &str) -> Result<BTreeMap<String, PackageManager>, String> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| format!("`{}` must be a table", key))?;
+
+    table
+        .iter()
+        .map(|(name, manager)| {
+            manager
+                .as_str()
+                .and_then(PackageManager::from_name)
+                .map(|manager| (name.clone(), manager))
+                .ok_or_else(|| {
+                    format!(
+                        "`{}.{}` must be one of npm, yarn, pnpm, bun or deno",
+                        key, name
+                    )
+                })
+        })
+        .collect()
+}
+
+fn string_map(value: &Value, key: &str) -> Result<BTreeMap<String, String>, String> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| format!("`{}` must be a table", key))?;
+
+    table
+        .iter()
+        .map(|(name, value)| {
+            value
+                .as_str()
+                .map(|value| (name.clone(), value.to_string()))
+                .ok_or_else(|| format!("`{}.{}` must be a string", key, name))
+        })
+        .collect()
+}
+
+fn binary_map(value: &Value, key: &str) -> Result<BTreeMap<String, String>, String> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| format!("`{}` must be a table", key))?;
+
+    table
+        .iter()
+        .map(|(name, binary)| {
+            let manager = PackageManager::from_name(name).ok_or_else(|| {
+                format!(
+                    "`{}.{}` is not one of npm, yarn, pnpm, bun or deno",
+                    key, name
+                )
+            })?;
+            let binary = binary
+                .as_str()
+                .ok_or_else(|| format!("`{}.{}` must be a string", key, name))?;
+
+            Ok((manager.name().to_string(), binary.to_string()))
+        })
+        .collect()
+}
+
+fn command_map(value: &Value, key: &str) -> Result<BTreeMap<String, Vec<String>>, String> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| format!("`{}` must be a table", key))?;
+
+    table
+        .iter()
+        .map(|(name, commands)| {
+            let manager = PackageManager::from_name(name).ok_or_else(|| {
+                format!(
+                    "`{}.{}` is not one of npm, yarn, pnpm, bun or deno",
+                    key, name
+                )
+            })?;
+            let commands = string_array(commands, &format!("{}.{}", key, name))?;
+
+            Ok((manager.name().to_string(), commands))
+        })
+        .collect()
+}
+
+// Either `dev_alias = "start"` or `dev_alias = { script = "start", run = false }`
+fn dev_alias(value: &Value) -> Result<DevAlias, String> {
+    if let Some(script) = value.as_str() {
+        return Ok(DevAlias {
+            script: script.to_string(),
+            run: None,
+        });
+    }
+
+    let error = || "`dev_alias` must be a script name or a table with `script`".to_string();
+    let table = value.as_table().ok_or_else(error)?;
+
+    let script = table
+        .get("script")
+        .and_then(|script| script.as_str())
+        .ok_or_else(error)?;
+    let run = table
+        .get("run")
+        .map(|run| bool_value(run, "dev_alias.run"))
+        .transpose()?;
+
+    Ok(DevAlias {
+        script: script.to_string(),
+        run,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    const PROFILES: &str = r#"
+install_flags = ["--prefer-offline"]
+
+[profiles.ci]
+install_flags = ["--no-audit"]
+autocorrect = false
+"#;
+
+    #[test]
+    fn profile_overrides_project_settings() {
+        let dir = TempDir::new();
+        dir.write(PROJECT_CONFIG, PROFILES);
+
+        let config = Config::load(dir.path(), Some("ci")).unwrap();
+
+        assert_eq!(config.install_flags, ["--no-audit"]);
+        assert!(!config.autocorrect);
+        assert_eq!(
+            config.sources.get("autocorrect"),
+            Some(&Source::Profile("ci".to_string()))
+        );
+    }
+
+    #[test]
+    fn profile_only_applies_when_selected() {
+        let dir = TempDir::new();
+        dir.write(PROJECT_CONFIG, PROFILES);
+
+        let config = Config::load(dir.path(), None).unwrap();
+
+        assert_eq!(config.install_flags, ["--prefer-offline"]);
+        assert!(config.autocorrect);
+    }
+
+    #[test]
+    fn unknown_profile_is_an_error() {
+        let dir = TempDir::new();
+        dir.write(PROJECT_CONFIG, PROFILES);
+
+        assert_eq!(
+            Config::load(dir.path(), Some("staging")),
+            Err("unknown profile `staging`".to_string())
+        );
+    }
+
+    #[test]
+    fn dump_labels_the_setting_that_wins() {
+        let dir = TempDir::new();
+        let global = dir.write(
+            "global/config.toml",
+            "autocorrect = false\ntask_files = true\n",
+        );
+        let project = dir.write(PROJECT_CONFIG, "autocorrect = true\n");
+
+        let dump = Config::load_with_global(Some(global.clone()), dir.path(), None)
+            .unwrap()
+            .dump();
+
+        let line = |key: &str| dump.lines().find(|line| line.starts_with(key)).unwrap();
+        assert_eq!(
+            line("autocorrect "),
+            format!("autocorrect = true # project ({})", project.display())
+        );
+        assert_eq!(
+            line("task_files "),
+            format!("task_files = true # global ({})", global.display())
+        );
+        assert_eq!(line("prefer_deno "), "prefer_deno = false # default");
+    }
+
+    #[test]
+    fn nrc_applies_unless_nrc_toml_overrides_it() {
+        let dir = TempDir::new();
+        dir.write(
+            LEGACY_CONFIG,
+            "# legacy\nautocorrect=false\ntask_files = true\nregistry=https://mirror/\n",
+        );
+
+        let config = Config::load(dir.path(), None).unwrap();
+        assert!(!config.autocorrect);
+        assert!(config.task_files);
+        assert_eq!(config.registry.as_deref(), Some("https://mirror/"));
+
+        dir.write(PROJECT_CONFIG, "autocorrect = true\n");
+
+        let config = Config::load(dir.path(), None).unwrap();
+        assert!(config.autocorrect);
+        assert!(config.task_files);
+    }
+
+    #[test]
+    fn project_config_is_found_from_a_subdirectory() {
+        let dir = TempDir::new();
+        let project = dir.write(PROJECT_CONFIG, "deny = [\"publish\"]\n");
+        dir.write(LEGACY_CONFIG, "task_files=true\n");
+        let app = dir.write("packages/app/package.json", "{}");
+
+        let config = Config::load(app.parent().unwrap(), None).unwrap();
+        assert_eq!(config.deny, ["publish"]);
+        assert!(config.task_files);
+        assert_eq!(config.sources.get("deny"), Some(&Source::Project(project)));
+    }
+
+    #[test]
+    fn closest_project_config_wins_over_one_further_up() {
+        let dir = TempDir::new();
+        dir.write(PROJECT_CONFIG, "task_files = true\n");
+        let app = dir.write("packages/app/.nrc.toml", "autocorrect = false\n");
+
+        let config = Config::load(app.parent().unwrap(), None).unwrap();
+        assert!(!config.autocorrect);
+        assert!(!config.task_files);
+    }
+
+    #[test]
+    fn set_adds_a_new_key_and_keeps_the_others() {
+        let dir = TempDir::new();
+        let path = dir.path().join("n/config.toml");
+
+        set_setting(&path, "registry", "https://npm.example.com").unwrap();
+        set_setting(&path, "binaries.pnpm", "corp-pnpm").unwrap();
+
+        assert_eq!(
+            get_setting(&path, "registry"),
+            Ok(Value::String("https://npm.example.com".to_string()))
+        );
+        assert_eq!(
+            get_setting(&path, "binaries.pnpm"),
+            Ok(Value::String("corp-pnpm".to_string()))
+        );
+    }
+
+    #[test]
+    fn set_overwrites_an_existing_key() {
+        let dir = TempDir::new();
+        let path = dir.write(
+            "config.toml",
+            "autocorrect = true\n\n[binaries]\nyarn = \"y\"\n",
+        );
+
+        set_setting(&path, "autocorrect", "false").unwrap();
+
+        assert_eq!(get_setting(&path, "autocorrect"), Ok(Value::Boolean(false)));
+        assert_eq!(
+            get_setting(&path, "binaries.yarn"),
+            Ok(Value::String("y".to_string()))
+        );
+        assert!(set_setting(&path, "autocorrect", "sometimes").is_err());
+        assert_eq!(get_setting(&path, "autocorrect"), Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn get_reads_a_nested_value() {
+        let dir = TempDir::new();
+        let path = dir.write("config.toml", "[node_env]\nbuild = \"staging\"\n");
+
+        assert_eq!(
+            get_setting(&path, "node_env.build"),
+            Ok(Value::String("staging".to_string()))
+        );
+        assert!(get_setting(&path, "node_env.dev").is_err());
+    }
+
+    #[test]
+    fn unknown_behavior_version_warns_and_uses_the_latest() {
+        assert_eq!(
+            known_behavior(7),
+            Err("unknown behavior_version 7, this n knows 1 to 2 and uses 2".to_string())
+        );
+        assert_eq!(known_behavior(1), Ok(1));
+
+        let table = toml::parse("behavior_version = 7\n").unwrap();
+        assert_eq!(
+            Config::from_table(&table).unwrap().behavior_version,
+            LATEST_BEHAVIOR
+        );
+    }
+}
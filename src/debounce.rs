@@ -0,0 +1,130 @@
+// Coalesces bursts of identical invocations. Every invocation writes its own token to a
+// file in the cache dir and waits; only the one whose token survives the quiet period runs.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cache;
+
+// Where the time for tokens and the wait come from, so tests don't have to sleep
+pub trait Clock {
+    // Time since the unix epoch
+    fn now(&self) -> Duration;
+    fn sleep(&self, duration: Duration);
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+// Returns true if no newer invocation with the same `dir` and `args` showed up within `after`
+pub fn settle(dir: &Path, args: &[String], after: Duration) -> Result<bool, String> {
+    let cache_dir = cache::cache_dir().ok_or("could not determine the cache directory")?;
+    settle_in(&cache_dir, dir, args, after, &SystemClock)
+}
+
+fn settle_in(
+    cache_dir: &Path,
+    dir: &Path,
+    args: &[String],
+    after: Duration,
+    clock: &dyn Clock,
+) -> Result<bool, String> {
+    fs::create_dir_all(cache_dir)
+        .map_err(|e| format!("failed to create {}: {}", cache_dir.display(), e))?;
+
+    let intent = intent_path(cache_dir, dir, args);
+    let token = format!("{}-{}", process::id(), clock.now().as_nanos());
+
+    fs::write(&intent, &token)
+        .map_err(|e| format!("failed to write {}: {}", intent.display(), e))?;
+
+    clock.sleep(after);
+
+    // The winner cleans up, so intents don't pile up in the cache dir
+    let won = fs::read_to_string(&intent).is_ok_and(|latest| latest == token);
+    if won {
+        let _ = fs::remove_file(&intent);
+    }
+    Ok(won)
+}
+
+fn intent_path(cache_dir: &Path, dir: &Path, args: &[String]) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    dir.hash(&mut hasher);
+    args.hash(&mut hasher);
+    cache_dir.join(format!("debounce-{:016x}", hasher.finish()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    type Hook = Box<dyn FnOnce(&FakeClock)>;
+
+    // Ticks on every read and lets a test run another invocation while one is waiting
+    struct FakeClock {
+        now: Cell<Duration>,
+        during_sleep: RefCell<Option<Hook>>,
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            self.now.set(self.now.get() + Duration::from_millis(1));
+            self.now.get()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.now.set(self.now.get() + duration);
+            if let Some(during_sleep) = self.during_sleep.take() {
+                during_sleep(self);
+            }
+        }
+    }
+
+    #[test]
+    fn two_rapid_intents_collapse_to_one_run() {
+        let cache = TempDir::new();
+        let args = vec!["build".to_string()];
+        let after = Duration::from_millis(200);
+
+        // The second invocation arrives while the first one is still waiting
+        let second = Rc::new(Cell::new(None));
+        let clock = FakeClock {
+            now: Cell::new(Duration::ZERO),
+            during_sleep: RefCell::new(Some(Box::new({
+                let cache = cache.path().to_path_buf();
+                let args = args.clone();
+                let second = Rc::clone(&second);
+                move |clock: &FakeClock| {
+                    let ran = settle_in(&cache, Path::new("/project"), &args, after, clock);
+                    second.set(Some(ran.unwrap()));
+                }
+            }))),
+        };
+
+        let first = settle_in(cache.path(), Path::new("/project"), &args, after, &clock);
+
+        assert_eq!(first, Ok(false));
+        assert_eq!(second.get(), Some(true));
+        assert!(fs::read_dir(cache.path()).unwrap().next().is_none());
+    }
+}
@@ -0,0 +1,819 @@
+use std::cmp::Reverse;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::Config;
+use crate::ignore::IgnoreFile;
+use crate::json;
+use crate::log;
+use crate::package_manager::PackageManager;
+use crate::toml;
+
+const LOCKFILES: &[(&str, PackageManager)] = &[
+    ("package-lock.json", PackageManager::Npm),
+    ("yarn.lock", PackageManager::Yarn),
+    ("bun.lockb", PackageManager::Bun),
+    ("pnpm-lock.yaml", PackageManager::Pnpm),
+];
+
+// Directories managers leave in the project, the weakest hint there is
+const CONFIG_DIRS: &[(&str, PackageManager)] = &[
+    (".yarn", PackageManager::Yarn),
+    (".pnpm-store", PackageManager::Pnpm),
+    (".npm", PackageManager::Npm),
+];
+
+// Kept apart from the lockfiles above, projects using deno's npm support often have both
+const DENO_MARKERS: &[&str] = &["deno.lock", "deno.json", "deno.jsonc"];
+
+const MISE_CONFIGS: &[&str] = &[".mise.toml", "mise.toml"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub manager: PackageManager,
+    // The directory the manager was detected in
+    pub root: PathBuf,
+    pub source: DetectionSource,
+}
+
+// Where a detected manager came from, listed from the highest precedence to the lowest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetectionSource {
+    Flag,
+    Env,
+    PackageManagerField,
+    ToolVersions,
+    Mise,
+    Lockfile,
+    Deno,
+    Bunfig,
+    NodeModules,
+    ConfigDir,
+    Dockerfile,
+    UserAgent,
+}
+
+impl fmt::Display for DetectionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectionSource::Flag => f.write_str("--manager"),
+            DetectionSource::Env => f.write_str("N_PACKAGE_MANAGER"),
+            DetectionSource::PackageManagerField => f.write_str("packageManager"),
+            DetectionSource::ToolVersions => f.write_str(".tool-versions"),
+            DetectionSource::Mise => f.write_str("mise"),
+            DetectionSource::Lockfile => f.write_str("lockfile"),
+            DetectionSource::Deno => f.write_str("deno.json or deno.lock"),
+            DetectionSource::Bunfig => f.write_str("bunfig.toml"),
+            DetectionSource::NodeModules => f.write_str("node_modules"),
+            DetectionSource::ConfigDir => f.write_str("config directory"),
+            DetectionSource::Dockerfile => f.write_str("Dockerfile"),
+            DetectionSource::UserAgent => f.write_str("user agent"),
+        }
+    }
+}
+
+// Picks the manager for `dir`: `--manager` > `N_PACKAGE_MANAGER` > the nearest
+// directory with a `packageManager` field, `.tool-versions` or lockfile > the
+// user agent of the manager that invoked n
+pub fn resolve_manager(
+    dir: &Path,
+    config: &Config,
+    flag: Option<PackageManager>,
+) -> Option<Detection> {
+    log::trace(
+        "detection_start",
+        vec![("dir", dir.display().to_string().into())],
+    );
+
+    let detection = detect_manager(dir, config, flag, &|name| env::var(name).ok());
+
+    log::trace(
+        "manager_resolved",
+        vec![
+            (
+                "manager",
+                detection.as_ref().map(|d| d.manager.to_string()).into(),
+            ),
+            (
+                "source",
+                detection.as_ref().map(|d| d.source.to_string()).into(),
+            ),
+            (
+                "root",
+                detection
+                    .as_ref()
+                    .map(|d| d.root.display().to_string())
+                    .into(),
+            ),
+        ],
+    );
+
+    detection
+}
+
+// `var` looks up environment variables, so tests don't have to touch the real ones
+fn detect_manager(
+    dir: &Path,
+    config: &Config,
+    flag: Option<PackageManager>,
+    var: &dyn Fn(&str) -> Option<String>,
+) -> Option<Detection> {
+    let explicit = |manager, source| Detection {
+        manager,
+        root: dir.to_path_buf(),
+        source,
+    };
+
+    if let Some(manager) = flag {
+        return Some(explicit(manager, DetectionSource::Flag));
+    }
+
+    if let Some(manager) = env_manager(var("N_PACKAGE_MANAGER")) {
+        return Some(explicit(manager, DetectionSource::Env));
+    }
+
+    let nignore = IgnoreFile::find(dir);
+
+    let detected = dir.ancestors().find_map(|candidate| {
+        let ignored_by_file = nignore
+            .as_ref()
+            .is_some_and(|nignore| nignore.is_ignored(candidate));
+
+        if ignored_by_file || is_ignored(candidate, config) {
+            log::trace(
+                "directory_scanned",
+                vec![
+                    ("dir", candidate.display().to_string().into()),
+                    ("ignored", true.into()),
+                ],
+            );
+            return None;
+        }
+
+        let found = check_directory_for_package_manager(candidate, config);
+
+        log::trace(
+            "directory_scanned",
+            vec![
+                ("dir", candidate.display().to_string().into()),
+                ("ignored", false.into()),
+                ("manager", found.map(|(m, _)| m.to_string()).into()),
+                ("source", found.map(|(_, s)| s.to_string()).into()),
+            ],
+        );
+
+        found.map(|(manager, source)| Detection {
+            manager,
+            root: candidate.to_path_buf(),
+            source,
+        })
+    });
+
+    detected.or_else(|| {
+        user_agent_manager(var("npm_config_user_agent"))
+            .map(|manager| explicit(manager, DetectionSource::UserAgent))
+    })
+}
+
+pub fn check_directory_for_package_manager(
+    dir: &Path,
+    config: &Config,
+) -> Option<(PackageManager, DetectionSource)> {
+    // Version manager files, asdf's and then mise's
+    let tool_versions = || {
+        read_tool_versions(dir)
+            .map(|manager| (manager, DetectionSource::ToolVersions))
+            .or_else(|| read_mise_config(dir).map(|manager| (manager, DetectionSource::Mise)))
+    };
+    // Next to an npm-family lockfile deno only wins with `prefer_deno`, scripts written
+    // for node run more reliably through the node managers
+    let lockfile = || {
+        let npm_family =
+            || find_lockfile(dir, config).map(|manager| (manager, DetectionSource::Lockfile));
+        let deno = || {
+            DENO_MARKERS
+                .iter()
+                .any(|marker| dir.join(marker).is_file())
+                .then_some((PackageManager::Deno, DetectionSource::Deno))
+        };
+
+        if config.prefer_deno {
+            deno().or_else(npm_family)
+        } else {
+            npm_family().or_else(deno)
+        }
+    };
+
+    let files = || {
+        if config.prefer_tool_versions {
+            tool_versions().or_else(lockfile)
+        } else {
+            lockfile().or_else(tool_versions)
+        }
+    };
+    let field = || {
+        read_package_manager_field(dir)
+            .map(|manager| (manager, DetectionSource::PackageManagerField))
+    };
+
+    // Behavior 1 only fell back to the `packageManager` field, 2 lets it beat everything
+    let detected = if config.behavior_version < 2 {
+        files().or_else(field)
+    } else {
+        field().or_else(files)
+    };
+
+    // A bunfig.toml without a lockfile still means bun, but any lockfile beats it
+    detected
+        .or_else(|| {
+            dir.join("bunfig.toml")
+                .is_file()
+                .then_some((PackageManager::Bun, DetectionSource::Bunfig))
+        })
+        .or_else(|| {
+            // What pnpm leaves in node_modules, e.g. in the lockfile-less output of `pnpm deploy`
+            let node_modules = dir.join("node_modules");
+            let installed_by_pnpm =
+                node_modules.join(".modules.yaml").is_file() || node_modules.join(".pnpm").is_dir();
+
+            installed_by_pnpm.then_some((PackageManager::Pnpm, DetectionSource::NodeModules))
+        })
+        .or_else(|| {
+            CONFIG_DIRS
+                .iter()
+                .find(|(config_dir, _)| dir.join(config_dir).is_dir())
+                .map(|(_, manager)| (*manager, DetectionSource::ConfigDir))
+        })
+        .or_else(|| read_dockerfile_hint(dir).map(|manager| (manager, DetectionSource::Dockerfile)))
+}
+
+// A `# n:manager=pnpm` line in the Dockerfile, for builds without anything else to go by
+fn read_dockerfile_hint(dir: &Path) -> Option<PackageManager> {
+    let contents = fs::read_to_string(dir.join("Dockerfile")).ok()?;
+
+    contents.lines().find_map(|line| {
+        let name = line.trim().strip_prefix("# n:manager=")?;
+        PackageManager::from_name(name.trim())
+    })
+}
+
+// Why `resolve_manager` came back empty, for telling the user what to fix
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotFound {
+    // No directory up to the filesystem root had anything to go by
+    NoMarkers { searched: usize },
+    // The nearest hint sits in a directory that `ignore_dirs` or `.nignore` skips
+    Ignored { dir: PathBuf },
+}
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotFound::NoMarkers { searched } => write!(
+                f,
+                "no lockfile or packageManager field found within {} parent directories",
+                searched.saturating_sub(1)
+            ),
+            NotFound::Ignored { dir } => write!(
+                f,
+                "the nearest hint is in {}, which is ignored for detection",
+                dir.display()
+            ),
+        }
+    }
+}
+
+// Walks the same directories as `resolve_manager`, this time looking inside ignored ones
+pub fn explain_not_found(dir: &Path, config: &Config) -> NotFound {
+    let nignore = IgnoreFile::find(dir);
+
+    let ignored = dir.ancestors().find(|candidate| {
+        let ignored_by_file = nignore
+            .as_ref()
+            .is_some_and(|nignore| nignore.is_ignored(candidate));
+
+        (ignored_by_file || is_ignored(candidate, config))
+            && check_directory_for_package_manager(candidate, config).is_some()
+    });
+
+    match ignored {
+        Some(ignored) => NotFound::Ignored {
+            dir: ignored.to_path_buf(),
+        },
+        None => NotFound::NoMarkers {
+            searched: dir.ancestors().count(),
+        },
+    }
+}
+
+// An extracted published package: a package.json with `bin` but no lockfile and no
+// workspace, nothing in it says which manager its authors used
+pub fn is_published_package(dir: &Path) -> bool {
+    let Some(package_json) = fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|contents| json::parse(&contents).ok())
+    else {
+        return false;
+    };
+
+    let has_lockfile = LOCKFILES
+        .iter()
+        .map(|(lockfile, _)| *lockfile)
+        .chain(DENO_MARKERS.iter().copied())
+        .any(|lockfile| dir.join(lockfile).is_file());
+    let is_workspace =
+        package_json.get("workspaces").is_some() || dir.join("pnpm-workspace.yaml").is_file();
+
+    package_json.get("bin").is_some() && !has_lockfile && !is_workspace
+}
+
+// Stray lockfiles in build output or vendored code shouldn't decide the manager
+fn is_ignored(dir: &Path, config: &Config) -> bool {
+    dir.file_name().is_some_and(|name| {
+        config
+            .ignore_dirs
+            .iter()
+            .any(|ignored| name == ignored.as_str())
+    })
+}
+
+fn find_lockfile(dir: &Path, config: &Config) -> Option<PackageManager> {
+    let custom = config
+        .lockfiles
+        .iter()
+        .find(|(lockfile, _)| dir.join(lockfile).is_file())
+        .map(|(_, manager)| *manager);
+
+    custom.or_else(|| {
+        LOCKFILES
+            .iter()
+            .find(|(lockfile, _)| dir.join(lockfile).is_file())
+            .map(|(_, manager)| *manager)
+    })
+}
+
+// Lockfiles in `dir` older than the newest one, most likely left over from switching managers
+pub fn find_stale_lockfiles(dir: &Path, config: &Config) -> Vec<(PathBuf, PathBuf)> {
+    let mut lockfiles: Vec<(PathBuf, SystemTime)> = config
+        .lockfiles
+        .keys()
+        .map(String::as_str)
+        .chain(LOCKFILES.iter().map(|(lockfile, _)| *lockfile))
+        .filter_map(|lockfile| {
+            let path = dir.join(lockfile);
+            let modified = fs::metadata(&path).ok()?.modified().ok()?;
+            Some((path, modified))
+        })
+        .collect();
+
+    lockfiles.sort_by_key(|(_, modified)| Reverse(*modified));
+
+    let Some((newest, newest_modified)) = lockfiles.first().cloned() else {
+        return Vec::new();
+    };
+
+    lockfiles
+        .into_iter()
+        .filter(|(_, modified)| *modified < newest_modified)
+        .map(|(path, _)| (path, newest.clone()))
+        .collect()
+}
+
+// asdf's `.tool-versions` lists one `<tool> <version>` pair per line
+fn read_tool_versions(dir: &Path) -> Option<PackageManager> {
+    let contents = fs::read_to_string(dir.join(".tool-versions")).ok()?;
+
+    contents.lines().find_map(|line| {
+        let line = line.split('#').next().unwrap_or_default();
+        line.split_whitespace().next().and_then(version_managed)
+    })
+}
+
+// mise declares tools in the `[tools]` table of `.mise.toml` or `mise.toml`
+fn read_mise_config(dir: &Path) -> Option<PackageManager> {
+    MISE_CONFIGS.iter().find_map(|name| {
+        let contents = fs::read_to_string(dir.join(name)).ok()?;
+        let config = toml::parse(&contents).ok()?;
+
+        config
+            .get("tools")?
+            .as_table()?
+            .keys()
+            .find_map(|tool| version_managed(tool))
+    })
+}
+
+// deno shows up in version manager files as a runtime next to node, not as the manager
+fn version_managed(tool: &str) -> Option<PackageManager> {
+    PackageManager::from_name(tool).filter(|manager| *manager != PackageManager::Deno)
+}
+
+// corepack's `"packageManager": "pnpm@8.6.0"`
+pub fn read_package_manager_field(dir: &Path) -> Option<PackageManager> {
+    let contents = fs::read_to_string(dir.join("package.json")).ok()?;
+    let package_json = json::parse(&contents).ok()?;
+
+    let field = package_json.get("packageManager")?.as_str()?;
+    PackageManager::from_name(field.split('@').next().unwrap_or_default())
+}
+
+fn env_manager(value: Option<String>) -> Option<PackageManager> {
+    value.and_then(|value| PackageManager::from_name(value.trim()))
+}
+
+// Managers set `npm_config_user_agent` to e.g. `pnpm/8.6.0 npm/? node/v18.16.0`
+fn user_agent_manager(user_agent: Option<String>) -> Option<PackageManager> {
+    let user_agent = user_agent?;
+    let name = user_agent.split('/').next()?;
+    PackageManager::from_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{set_modified, TempDir};
+
+    fn detect(dir: &TempDir, config: &Config) -> Option<(PackageManager, DetectionSource)> {
+        check_directory_for_package_manager(dir.path(), config)
+    }
+
+    #[test]
+    fn custom_lockfile_mapping_is_honored() {
+        let dir = TempDir::new();
+        dir.write("corp.lock", "");
+        let config = Config {
+            lockfiles: [("corp.lock".to_string(), PackageManager::Pnpm)].into(),
+            ..Config::default()
+        };
+
+        assert_eq!(
+            detect(&dir, &config),
+            Some((PackageManager::Pnpm, DetectionSource::Lockfile))
+        );
+        assert_eq!(detect(&dir, &Config::default()), None);
+    }
+
+    #[test]
+    fn custom_lockfile_mapping_wins_over_builtin_ones() {
+        let dir = TempDir::new();
+        dir.write("corp.lock", "");
+        dir.write("package-lock.json", "{}");
+        let config = Config {
+            lockfiles: [("corp.lock".to_string(), PackageManager::Pnpm)].into(),
+            ..Config::default()
+        };
+
+        assert_eq!(
+            detect(&dir, &config),
+            Some((PackageManager::Pnpm, DetectionSource::Lockfile))
+        );
+    }
+
+    #[test]
+    fn tool_versions_names_the_manager() {
+        let dir = TempDir::new();
+        dir.write(".tool-versions", "nodejs 20.11.0\npnpm 9.1.0 # pinned\n");
+
+        assert_eq!(
+            detect(&dir, &Config::default()),
+            Some((PackageManager::Pnpm, DetectionSource::ToolVersions))
+        );
+    }
+
+    #[test]
+    fn tool_versions_without_a_manager_is_no_hint() {
+        let dir = TempDir::new();
+        dir.write(".tool-versions", "nodejs 20.11.0\ndeno 1.40.0\n");
+
+        assert_eq!(detect(&dir, &Config::default()), None);
+    }
+
+    fn detect_with(
+        dir: &TempDir,
+        flag: Option<PackageManager>,
+        vars: &[(&str, &str)],
+    ) -> Option<(PackageManager, DetectionSource)> {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        };
+        detect_manager(dir.path(), &Config::default(), flag, &var)
+            .map(|detection| (detection.manager, detection.source))
+    }
+
+    #[test]
+    fn each_level_overrides_the_ones_below() {
+        use DetectionSource as Source;
+        use PackageManager::{Bun, Npm, Pnpm, Yarn};
+
+        // From the strongest marker to the weakest, each naming another manager than the next
+        let markers = [
+            (
+                "package.json",
+                r#"{"packageManager":"pnpm@9.1.0"}"#,
+                Pnpm,
+                Source::PackageManagerField,
+            ),
+            (
+                ".tool-versions",
+                "yarn 1.22.22\n",
+                Yarn,
+                Source::ToolVersions,
+            ),
+            (".mise.toml", "[tools]\npnpm = \"9\"\n", Pnpm, Source::Mise),
+            ("package-lock.json", "{}", Npm, Source::Lockfile),
+            ("deno.json", "{}", PackageManager::Deno, Source::Deno),
+            ("bunfig.toml", "", Bun, Source::Bunfig),
+            ("node_modules/.modules.yaml", "", Pnpm, Source::NodeModules),
+            (".yarn/releases/yarn.cjs", "", Yarn, Source::ConfigDir),
+            ("Dockerfile", "# n:manager=bun\n", Bun, Source::Dockerfile),
+        ];
+        let dir = TempDir::new();
+        for (name, contents, ..) in &markers {
+            dir.write(name, contents);
+        }
+        let env = [("N_PACKAGE_MANAGER", "yarn")];
+        let user_agent = [("npm_config_user_agent", "npm/10.2.0 node/v20.11.0")];
+
+        assert_eq!(
+            detect_with(&dir, Some(Bun), &env),
+            Some((Bun, Source::Flag))
+        );
+        assert_eq!(detect_with(&dir, None, &env), Some((Yarn, Source::Env)));
+
+        for (name, _, manager, source) in markers {
+            assert_eq!(
+                detect_with(&dir, None, &user_agent),
+                Some((manager, source)),
+                "{}",
+                name
+            );
+
+            let path = dir.path().join(name.split('/').next().unwrap());
+            if path.is_dir() {
+                fs::remove_dir_all(path).unwrap();
+            } else {
+                fs::remove_file(path).unwrap();
+            }
+        }
+
+        assert_eq!(
+            detect_with(&dir, None, &user_agent),
+            Some((Npm, Source::UserAgent))
+        );
+    }
+
+    #[test]
+    fn lockfile_in_an_ignored_dir_is_skipped() {
+        let dir = TempDir::new();
+        dir.write("pnpm-lock.yaml", "");
+        dir.write("dist/yarn.lock", "");
+        let dist = dir.path().join("dist");
+        let config = Config {
+            ignore_dirs: vec!["dist".to_string()],
+            ..Config::default()
+        };
+
+        let detection = detect_manager(&dist, &config, None, &|_| None).unwrap();
+        assert_eq!(detection.manager, PackageManager::Pnpm);
+        assert_eq!(detection.root, dir.path());
+
+        let detection = detect_manager(&dist, &Config::default(), None, &|_| None).unwrap();
+        assert_eq!(detection.manager, PackageManager::Yarn);
+    }
+
+    #[test]
+    fn mise_config_names_the_manager() {
+        for name in MISE_CONFIGS {
+            let dir = TempDir::new();
+            dir.write(name, "[tools]\nnode = \"20\"\npnpm = \"9.1.0\"\n");
+
+            assert_eq!(
+                detect(&dir, &Config::default()),
+                Some((PackageManager::Pnpm, DetectionSource::Mise))
+            );
+        }
+    }
+
+    #[test]
+    fn older_lockfile_is_stale() {
+        let dir = TempDir::new();
+        let yarn = dir.write("yarn.lock", "");
+        let pnpm = dir.write("pnpm-lock.yaml", "");
+        set_modified(&yarn, 3600);
+        set_modified(&pnpm, 60);
+
+        assert_eq!(
+            find_stale_lockfiles(dir.path(), &Config::default()),
+            [(yarn, pnpm)]
+        );
+    }
+
+    #[test]
+    fn single_lockfile_is_never_stale() {
+        let dir = TempDir::new();
+        dir.write("package-lock.json", "{}");
+
+        assert_eq!(find_stale_lockfiles(dir.path(), &Config::default()), []);
+    }
+
+    #[test]
+    fn bunfig_alone_means_bun() {
+        let dir = TempDir::new();
+        dir.write("bunfig.toml", "[install]\nexact = true\n");
+
+        assert_eq!(
+            detect(&dir, &Config::default()),
+            Some((PackageManager::Bun, DetectionSource::Bunfig))
+        );
+    }
+
+    #[test]
+    fn lockfile_beats_bunfig() {
+        let dir = TempDir::new();
+        dir.write("bunfig.toml", "");
+        dir.write("pnpm-lock.yaml", "");
+
+        assert_eq!(
+            detect(&dir, &Config::default()),
+            Some((PackageManager::Pnpm, DetectionSource::Lockfile))
+        );
+    }
+
+    #[test]
+    fn pnpm_node_modules_without_a_lockfile_mean_pnpm() {
+        let dir = TempDir::new();
+        dir.write("node_modules/.modules.yaml", "layoutVersion: 5\n");
+
+        assert_eq!(
+            detect(&dir, &Config::default()),
+            Some((PackageManager::Pnpm, DetectionSource::NodeModules))
+        );
+    }
+
+    #[test]
+    fn plain_node_modules_are_no_hint() {
+        let dir = TempDir::new();
+        dir.write("node_modules/lodash/package.json", "{}");
+
+        assert_eq!(detect(&dir, &Config::default()), None);
+    }
+
+    #[test]
+    fn nignore_skips_a_stray_lockfile_directory() {
+        let dir = TempDir::new();
+        dir.write("yarn.lock", "");
+        dir.write(".nignore", "# fixtures\nvendor/*\n!vendor/keep\n");
+        dir.write("vendor/legacy/package-lock.json", "{}");
+        dir.write("vendor/keep/pnpm-lock.yaml", "");
+        let src = dir
+            .write("vendor/legacy/src/index.js", "")
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        let kept = dir.path().join("vendor/keep");
+
+        let detect = |start: &Path| {
+            detect_manager(start, &Config::default(), None, &|_| None)
+                .map(|detection| (detection.manager, detection.root))
+        };
+
+        assert_eq!(
+            detect(&src),
+            Some((PackageManager::Yarn, dir.path().to_path_buf()))
+        );
+        assert_eq!(detect(&kept), Some((PackageManager::Pnpm, kept.clone())));
+    }
+
+    #[test]
+    fn yarn_dir_means_yarn_without_a_lockfile() {
+        let dir = TempDir::new();
+        dir.write("package.json", "{}");
+        fs::create_dir(dir.path().join(".yarn")).unwrap();
+
+        assert_eq!(
+            detect(&dir, &Config::default()),
+            Some((PackageManager::Yarn, DetectionSource::ConfigDir))
+        );
+
+        dir.write("package-lock.json", "{}");
+        assert_eq!(
+            detect(&dir, &Config::default()),
+            Some((PackageManager::Npm, DetectionSource::Lockfile))
+        );
+    }
+
+    #[test]
+    fn dockerfile_hint_is_the_last_resort() {
+        let dir = TempDir::new();
+        dir.write(
+            "Dockerfile",
+            "FROM node:20\n# n:manager=pnpm\nRUN corepack enable\n",
+        );
+
+        assert_eq!(
+            detect(&dir, &Config::default()),
+            Some((PackageManager::Pnpm, DetectionSource::Dockerfile))
+        );
+
+        dir.write("Dockerfile", "FROM node:20\n# manager=pnpm\n");
+        assert_eq!(detect(&dir, &Config::default()), None);
+    }
+
+    #[test]
+    fn deno_lock_next_to_an_npm_lockfile() {
+        let dir = TempDir::new();
+        dir.write("package.json", "{}");
+        dir.write("deno.lock", "{}");
+        dir.write("pnpm-lock.yaml", "");
+        let prefer_deno = Config {
+            prefer_deno: true,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            detect(&dir, &Config::default()),
+            Some((PackageManager::Pnpm, DetectionSource::Lockfile))
+        );
+        assert_eq!(
+            detect(&dir, &prefer_deno),
+            Some((PackageManager::Deno, DetectionSource::Deno))
+        );
+
+        dir.write("package.json", r#"{"packageManager":"deno@2.1.0"}"#);
+        let pinned = Config {
+            behavior_version: 2,
+            ..Config::default()
+        };
+        assert_eq!(
+            detect(&dir, &pinned),
+            Some((PackageManager::Deno, DetectionSource::PackageManagerField))
+        );
+    }
+
+    #[test]
+    fn not_found_explains_ignored_hints_apart_from_none() {
+        let dir = TempDir::new();
+        let empty = dir.path().join("empty");
+        fs::create_dir(&empty).unwrap();
+        let searched = empty.ancestors().count();
+
+        let reason = explain_not_found(&empty, &Config::default());
+        assert_eq!(reason, NotFound::NoMarkers { searched });
+        assert_eq!(
+            reason.to_string(),
+            format!(
+                "no lockfile or packageManager field found within {} parent directories",
+                searched - 1
+            )
+        );
+
+        dir.write(".nignore", "legacy/\n");
+        dir.write("legacy/yarn.lock", "");
+        let app = dir
+            .write("legacy/app/index.js", "")
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        let legacy = dir.path().join("legacy");
+
+        let reason = explain_not_found(&app, &Config::default());
+        assert_eq!(
+            reason,
+            NotFound::Ignored {
+                dir: legacy.clone()
+            }
+        );
+        assert_eq!(
+            reason.to_string(),
+            format!(
+                "the nearest hint is in {}, which is ignored for detection",
+                legacy.display()
+            )
+        );
+    }
+
+    #[test]
+    fn behavior_version_picks_the_precedence() {
+        let dir = TempDir::new();
+        dir.write("package.json", r#"{"packageManager":"pnpm@9.1.0"}"#);
+        dir.write("yarn.lock", "");
+        let pinned = |version: &str| {
+            let table = crate::toml::parse(&format!("behavior_version = {}\n", version)).unwrap();
+            Config::from_table(&table).unwrap()
+        };
+
+        assert_eq!(
+            detect(&dir, &pinned("1")),
+            Some((PackageManager::Yarn, DetectionSource::Lockfile))
+        );
+        assert_eq!(
+            detect(&dir, &pinned("2")),
+            Some((PackageManager::Pnpm, DetectionSource::PackageManagerField))
+        );
+    }
+}
@@ -0,0 +1,353 @@
+// `n doctor` looks for setups that make detection or installs misbehave. It only
+// reports, unless `--fix` is passed, then every fix is confirmed on its own.
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::detect::{find_stale_lockfiles, read_package_manager_field};
+use crate::error::Error;
+use crate::json;
+use crate::package_manager::PackageManager;
+use crate::runner::{ask, run_program, CommandRunner};
+
+struct Finding {
+    message: String,
+    fix: Option<Fix>,
+}
+
+enum Fix {
+    RemoveFile(PathBuf),
+    EnableCorepack,
+    AddRegistry { npmrc: PathBuf, registry: String },
+}
+
+impl Fix {
+    fn describe(&self) -> String {
+        match self {
+            Fix::RemoveFile(path) => format!("Remove {}", path.display()),
+            Fix::EnableCorepack => "Run `corepack enable`".to_string(),
+            Fix::AddRegistry { npmrc, registry } => {
+                format!("Add `registry={}` to {}", registry, npmrc.display())
+            }
+        }
+    }
+
+    fn apply(&self, runner: &dyn CommandRunner, root: &Path) -> Result<(), Error> {
+        match self {
+            Fix::RemoveFile(path) => fs::remove_file(path)
+                .map_err(|e| Error::Other(format!("failed to remove {}: {}", path.display(), e))),
+            Fix::EnableCorepack => {
+                run_program(runner, "corepack", &["enable".to_string()], root, None).map_err(
+                    |error| match error {
+                        Error::ProgramNotFound(_) => Error::CorepackNotFound,
+                        error => error,
+                    },
+                )
+            }
+            Fix::AddRegistry { npmrc, registry } => {
+                let write_error =
+                    |e| Error::Other(format!("failed to write {}: {}", npmrc.display(), e));
+
+                let existing = fs::read_to_string(npmrc).unwrap_or_default();
+                let separator = if existing.is_empty() || existing.ends_with('\n') {
+                    ""
+                } else {
+                    "\n"
+                };
+
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(npmrc)
+                    .and_then(|mut file| writeln!(file, "{}registry={}", separator, registry))
+                    .map_err(write_error)
+            }
+        }
+    }
+}
+
+pub fn run_doctor(
+    runner: &dyn CommandRunner,
+    root: &Path,
+    config: &Config,
+    fix: bool,
+    yes: bool,
+) -> Result<(), Error> {
+    let findings = collect_findings(root, config);
+
+    if findings.is_empty() {
+        println!("No problems found");
+    }
+    for finding in &findings {
+        println!("warning: {}", finding.message);
+    }
+
+    if !fix {
+        return Ok(());
+    }
+
+    for fix in findings.iter().filter_map(|finding| finding.fix.as_ref()) {
+        let description = fix.describe();
+
+        match ask(&format!("{}?", description), yes) {
+            Some(true) => {
+                fix.apply(runner, root)?;
+                println!("fixed: {}", description);
+            }
+            Some(false) => println!("skipped: {}", description),
+            None => println!("skipped: {} (no terminal to confirm, pass -y)", description),
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_findings(root: &Path, config: &Config) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for (stale, newest) in find_stale_lockfiles(root, config) {
+        findings.push(Finding {
+            message: format!(
+                "{} is older than {} and probably stale, consider removing it",
+                stale.display(),
+                file_name(&newest)
+            ),
+            fix: Some(Fix::RemoveFile(stale)),
+        });
+    }
+
+    // corepack doesn't manage deno
+    if let Some(manager) = read_package_manager_field(root) {
+        if manager != PackageManager::Deno && !is_corepack_shim(manager.name()) {
+            findings.push(Finding {
+                message: format!(
+                    "package.json pins {} through `packageManager`, but corepack isn't enabled",
+                    manager
+                ),
+                fix: Some(Fix::EnableCorepack),
+            });
+        }
+    }
+
+    for message in find_type_mismatches(root) {
+        findings.push(Finding { message, fix: None });
+    }
+
+    if let Some(registry) = &config.registry {
+        let npmrc = root.join(".npmrc");
+        let has_registry = fs::read_to_string(&npmrc).is_ok_and(|contents| {
+            contents
+                .lines()
+                .any(|line| line.trim_start().starts_with("registry"))
+        });
+
+        if !has_registry {
+            findings.push(Finding {
+                message: format!("{} doesn't set the registry {}", npmrc.display(), registry),
+                fix: Some(Fix::AddRegistry {
+                    npmrc,
+                    registry: registry.clone(),
+                }),
+            });
+        }
+    }
+
+    findings
+}
+
+// `.js` entrypoints whose syntax contradicts `"type"`, which decides how node loads them.
+// `.mjs` and `.cjs` load the same way whatever `"type"` says, so mixing them in is fine.
+fn find_type_mismatches(root: &Path) -> Vec<String> {
+    let Some(package_json) = fs::read_to_string(root.join("package.json"))
+        .ok()
+        .and_then(|contents| json::parse(&contents).ok())
+    else {
+        return Vec::new();
+    };
+
+    let module_type = package_json.get("type").and_then(json::Value::as_str);
+    let declared = match module_type {
+        Some(module_type) => format!("\"type\" is {}", module_type),
+        None => "there is no \"type\", so node loads it as commonjs".to_string(),
+    };
+
+    entrypoints(&package_json)
+        .into_iter()
+        .filter_map(|(place, file)| {
+            let source = fs::read_to_string(root.join(&file)).ok()?;
+            let kind = type_mismatch(module_type.unwrap_or("commonjs"), &file, &source)?;
+            Some(format!(
+                "{} runs {}, which looks like {}, but {}",
+                place, file, kind, declared
+            ))
+        })
+        .collect()
+}
+
+// What kind of code `file` is when node would load it as the other kind. Only source
+// written in one module syntax is judged, anything mixed is left alone.
+fn type_mismatch(module_type: &str, file: &str, source: &str) -> Option<&'static str> {
+    if Path::new(file).extension()? != "js" {
+        return None;
+    }
+
+    let esm = source.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with("import ") || line.starts_with("export ")
+    });
+    let commonjs = source.contains("require(") || source.contains("module.exports");
+
+    match (module_type == "module", esm, commonjs) {
+        (true, false, true) => Some("CommonJS"),
+        (false, true, false) => Some("an ES module"),
+        _ => None,
+    }
+}
+
+// `main`, every `bin` and the files scripts hand to `node`, with where they came from
+fn entrypoints(package_json: &json::Value) -> Vec<(String, String)> {
+    let mut entrypoints = Vec::new();
+
+    if let Some(main) = package_json.get("main").and_then(json::Value::as_str) {
+        entrypoints.push(("`main`".to_string(), main.to_string()));
+    }
+
+    match package_json.get("bin") {
+        Some(json::Value::String(bin)) => entrypoints.push(("`bin`".to_string(), bin.clone())),
+        Some(json::Value::Object(bins)) => {
+            for (name, bin) in bins {
+                if let Some(bin) = bin.as_str() {
+                    entrypoints.push((format!("`bin.{}`", name), bin.to_string()));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let scripts = package_json
+        .get("scripts")
+        .and_then(json::Value::as_object)
+        .unwrap_or_default();
+    for (name, command) in scripts {
+        let Some(command) = command.as_str() else {
+            continue;
+        };
+
+        let words: Vec<&str> = command.split_whitespace().collect();
+        for pair in words.windows(2) {
+            if pair[0] == "node" && !pair[1].starts_with('-') {
+                entrypoints.push((format!("script `{}`", name), pair[1].to_string()));
+            }
+        }
+    }
+
+    entrypoints
+}
+
+// With corepack enabled the manager on PATH is a link into corepack's shims
+fn is_corepack_shim(program: &str) -> bool {
+    let Some(paths) = env::var_os("PATH") else {
+        return false;
+    };
+
+    env::split_paths(&paths)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+        .and_then(|path| fs::canonicalize(path).ok())
+        .is_some_and(|path| path.to_string_lossy().contains("corepack"))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::RecordingRunner;
+    use crate::testing::{set_modified, TempDir};
+
+    #[test]
+    fn fix_removes_only_the_stale_lockfiles() {
+        let dir = TempDir::new();
+        dir.write("package.json", "{}");
+        let npm = dir.write("package-lock.json", "{}");
+        let yarn = dir.write("yarn.lock", "");
+        let pnpm = dir.write("pnpm-lock.yaml", "");
+        set_modified(&npm, 7200);
+        set_modified(&yarn, 3600);
+        set_modified(&pnpm, 60);
+        let runner = RecordingRunner::default();
+
+        run_doctor(&runner, dir.path(), &Config::default(), true, true).unwrap();
+
+        assert!(!npm.exists());
+        assert!(!yarn.exists());
+        assert!(pnpm.exists());
+        assert!(dir.path().join("package.json").exists());
+        assert!(runner.commands().is_empty());
+    }
+
+    #[test]
+    fn findings_alone_change_nothing() {
+        let dir = TempDir::new();
+        let yarn = dir.write("yarn.lock", "");
+        dir.write("pnpm-lock.yaml", "");
+        set_modified(&yarn, 3600);
+
+        run_doctor(
+            &RecordingRunner::default(),
+            dir.path(),
+            &Config::default(),
+            false,
+            true,
+        )
+        .unwrap();
+
+        assert!(yarn.exists());
+    }
+
+    #[test]
+    fn type_mismatch_judges_js_files_by_their_syntax() {
+        let esm = "import { run } from './run.js'\nexport default run\n";
+        let commonjs = "const run = require('./run')\nmodule.exports = run\n";
+
+        assert_eq!(
+            type_mismatch("commonjs", "index.js", esm),
+            Some("an ES module")
+        );
+        assert_eq!(
+            type_mismatch("module", "index.js", commonjs),
+            Some("CommonJS")
+        );
+        assert_eq!(type_mismatch("module", "index.js", esm), None);
+        assert_eq!(type_mismatch("commonjs", "index.js", commonjs), None);
+        // `.mjs` and `.cjs` decide for themselves
+        assert_eq!(type_mismatch("commonjs", "index.mjs", esm), None);
+        assert_eq!(type_mismatch("module", "index.cjs", commonjs), None);
+    }
+
+    #[test]
+    fn type_mismatch_is_reported_for_the_entrypoint() {
+        let dir = TempDir::new();
+        dir.write(
+            "package.json",
+            r#"{"main":"index.js","scripts":{"start":"node server.js"}}"#,
+        );
+        dir.write("index.js", "module.exports = {}\n");
+        dir.write("server.js", "import http from 'node:http'\n");
+
+        assert_eq!(
+            find_type_mismatches(dir.path()),
+            [
+                "script `start` runs server.js, which looks like an ES module, but there is no \
+              \"type\", so node loads it as commonjs"
+            ]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::cli::Options;
+use crate::commands::patch_commands;
+use crate::config::Config;
+use crate::detect::resolve_manager;
+use crate::error::Error;
+use crate::pool::{run_bounded, select_concurrency};
+use crate::recursive::{format_summary, RunResult};
+use crate::runner::{ensure_allowed, run_manager, CommandRunner, CommandSpec};
+use crate::workspace::{expand_glob, read_package_name};
+
+// Runs `args` (an install) in every project matched by `pattern`, each with the manager
+// and config detected for it, then prints a summary. Fails when any project failed.
+pub fn install_each(
+    runner: &dyn CommandRunner,
+    base: &Path,
+    pattern: &str,
+    args: &[String],
+    options: &Options,
+) -> Result<(), Error> {
+    let dirs: Vec<PathBuf> = expand_glob(base, pattern)
+        .into_iter()
+        .filter(|dir| dir.join("package.json").is_file())
+        .collect();
+
+    if dirs.is_empty() {
+        return Err(Error::Other(format!(
+            "`{}` matches no directory with a package.json",
+            pattern
+        )));
+    }
+
+    let concurrency = select_concurrency(options.concurrency, true);
+    let prefix_output = dirs.len() > 1;
+
+    let results = run_bounded(dirs, concurrency, |dir| {
+        let name = read_package_name(&dir);
+        let started = Instant::now();
+
+        let prefix = prefix_output.then(|| name.clone());
+        let exit_code = match install(runner, &dir, args, options, prefix) {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("n: {}: {}", name, error);
+                error.exit_code()
+            }
+        };
+
+        RunResult {
+            package: name,
+            exit_code,
+            duration: started.elapsed(),
+        }
+    });
+
+    eprint!("\n{}", format_summary(&results));
+
+    let failed = results
+        .iter()
+        .filter(|result| result.exit_code != 0)
+        .count();
+    if failed > 0 {
+        return Err(Error::Other(format!(
+            "install failed in {} of {} projects",
+            failed,
+            results.len()
+        )));
+    }
+
+    Ok(())
+}
+
+fn install(
+    runner: &dyn CommandRunner,
+    dir: &Path,
+    args: &[String],
+    options: &Options,
+    prefix: Option<String>,
+) -> Result<(), Error> {
+    let config = Config::load(dir, options.profile.as_deref()).map_err(Error::Config)?;
+    let detection =
+        resolve_manager(dir, &config, options.manager).ok_or(Error::NoManagerDetected)?;
+
+    let patched = patch_commands(detection.manager, args, &config);
+    ensure_allowed(args, &patched, &config.deny)?;
+    eprintln!(
+        "n: {} {} in {}",
+        detection.manager,
+        patched.join(" "),
+        dir.display()
+    );
+
+    let spec = CommandSpec {
+        program: config.binary(detection.manager).to_string(),
+        args: patched,
+        dir: dir.to_path_buf(),
+        timeout: options.timeout,
+        spinner: false,
+        env: Vec::new(),
+        prefix,
+        bail_on: None,
+    };
+
+    run_manager(runner, detection.manager, &spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::RecordingRunner;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn installs_every_matched_project_with_its_own_manager() {
+        let dir = TempDir::new();
+        dir.write("projects/web/package.json", r#"{"name":"web"}"#);
+        dir.write("projects/web/pnpm-lock.yaml", "");
+        dir.write("projects/api/package.json", r#"{"name":"api"}"#);
+        dir.write("projects/api/yarn.lock", "");
+        let runner = RecordingRunner::default();
+
+        let args = vec!["install".to_string()];
+        install_each(
+            &runner,
+            dir.path(),
+            "projects/*",
+            &args,
+            &Options::default(),
+        )
+        .unwrap();
+
+        let mut scheduled: Vec<(String, PathBuf)> = runner
+            .commands()
+            .into_iter()
+            .map(|spec| {
+                (
+                    format!("{} {}", spec.program, spec.args.join(" ")),
+                    spec.dir,
+                )
+            })
+            .collect();
+        scheduled.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            scheduled,
+            [
+                ("yarn install".to_string(), dir.path().join("projects/api")),
+                ("pnpm install".to_string(), dir.path().join("projects/web")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::json::{self, Value};
+use crate::package_manager::PackageManager;
+
+// Exit codes n documents for its own failures. A failing command exits with the
+// code of the package manager instead.
+pub const GENERAL_EXIT_CODE: i32 = 1;
+pub const USAGE_EXIT_CODE: i32 = 2;
+pub const NO_MANAGER_EXIT_CODE: i32 = 3;
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+pub const MANAGER_NOT_FOUND_EXIT_CODE: i32 = 127;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    // Invalid flags or arguments for n itself
+    Usage(String),
+    Config(String),
+    NoManagerDetected,
+    // A bare `n` in a project without scripts, there is nothing sensible to run
+    NoScripts,
+    // The manager binary isn't installed or not on PATH
+    ManagerNotFound(PackageManager),
+    ProgramNotFound(String),
+    // corepack ships with Node but isn't always on PATH
+    CorepackNotFound,
+    CommandFailed { program: String, code: i32 },
+    TimedOut { program: String, after: Duration },
+    Aborted,
+    // The command is listed under `deny`
+    Denied(String),
+    // `--print-manager` found no manager, the empty output says enough
+    NothingToPrint,
+    Other(String),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) | Error::NoScripts => USAGE_EXIT_CODE,
+            Error::NoManagerDetected => NO_MANAGER_EXIT_CODE,
+            Error::ManagerNotFound(_) | Error::ProgramNotFound(_) | Error::CorepackNotFound => {
+                MANAGER_NOT_FOUND_EXIT_CODE
+            }
+            Error::CommandFailed { code, .. } => *code,
+            Error::TimedOut { .. } => TIMEOUT_EXIT_CODE,
+            Error::Config(_)
+            | Error::Aborted
+            | Error::Denied(_)
+            | Error::NothingToPrint
+            | Error::Other(_) => GENERAL_EXIT_CODE,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Usage(_) => "usage",
+            Error::Config(_) => "config",
+            Error::NoManagerDetected => "no_manager_detected",
+            Error::NoScripts => "no_scripts",
+            Error::ManagerNotFound(_) => "manager_not_found",
+            Error::ProgramNotFound(_) => "program_not_found",
+            Error::CorepackNotFound => "corepack_not_found",
+            Error::CommandFailed { .. } => "command_failed",
+            Error::TimedOut { .. } => "timed_out",
+            Error::Aborted => "aborted",
+            Error::Denied(_) => "denied",
+            Error::NothingToPrint => "nothing_to_print",
+            Error::Other(_) => "error",
+        }
+    }
+
+    // The machine readable form printed with `--json`
+    pub fn to_json(&self) -> String {
+        let mut fields = vec![("error".to_string(), Value::String(self.kind().to_string()))];
+
+        match self {
+            Error::ManagerNotFound(manager) => {
+                fields.push(("manager".to_string(), Value::String(manager.to_string())));
+            }
+            Error::Denied(command) => {
+                fields.push(("command".to_string(), Value::String(command.clone())));
+            }
+            Error::ProgramNotFound(program) | Error::TimedOut { program, .. } => {
+                fields.push(("program".to_string(), Value::String(program.clone())));
+            }
+            Error::CommandFailed { program, code } => {
+                fields.push(("program".to_string(), Value::String(program.clone())));
+                fields.push(("code".to_string(), Value::Number(code.to_string())));
+            }
+            Error::Usage(message) | Error::Config(message) | Error::Other(message) => {
+                fields.push(("message".to_string(), Value::String(message.clone())));
+            }
+            Error::NoManagerDetected
+            | Error::NoScripts
+            | Error::CorepackNotFound
+            | Error::Aborted
+            | Error::NothingToPrint => {}
+        }
+
+        json::to_string(&Value::Object(fields))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) | Error::Config(message) | Error::Other(message) => {
+                f.write_str(message)
+            }
+            Error::NoManagerDetected => f.write_str("No package manager detected."),
+            Error::NoScripts => f.write_str("no scripts defined and no command given"),
+            Error::ManagerNotFound(manager) => {
+                write!(f, "`{}` is not installed or not on your PATH", manager)
+            }
+            Error::ProgramNotFound(program) => {
+                write!(f, "`{}` is not installed or not on your PATH", program)
+            }
+            Error::CorepackNotFound => f.write_str(
+                "corepack is not on your PATH, it ships with Node.js 16.9+ \
+                 or can be installed with `npm install -g corepack`",
+            ),
+            Error::CommandFailed { code, .. } => {
+                write!(f, "Command failed to execute (exit code {})", code)
+            }
+            Error::TimedOut { after, .. } => write!(f, "command timed out after {:?}", after),
+            Error::Aborted => f.write_str("aborted"),
+            Error::NothingToPrint => f.write_str("nothing to print"),
+            Error::Denied(command) => {
+                write!(f, "command '{}' is disabled by configuration", command)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every error with the exit code and `--json` shape the readme documents
+    fn documented() -> Vec<(Error, i32, &'static str)> {
+        vec![
+            (
+                Error::Config("bad".to_string()),
+                1,
+                r#"{"error":"config","message":"bad"}"#,
+            ),
+            (Error::Aborted, 1, r#"{"error":"aborted"}"#),
+            (Error::NothingToPrint, 1, r#"{"error":"nothing_to_print"}"#),
+            (
+                Error::Denied("publish".to_string()),
+                1,
+                r#"{"error":"denied","command":"publish"}"#,
+            ),
+            (
+                Error::Other("oops".to_string()),
+                1,
+                r#"{"error":"error","message":"oops"}"#,
+            ),
+            (
+                Error::Usage("bad flag".to_string()),
+                2,
+                r#"{"error":"usage","message":"bad flag"}"#,
+            ),
+            (Error::NoScripts, 2, r#"{"error":"no_scripts"}"#),
+            (
+                Error::NoManagerDetected,
+                3,
+                r#"{"error":"no_manager_detected"}"#,
+            ),
+            (
+                Error::TimedOut {
+                    program: "npm".to_string(),
+                    after: Duration::from_secs(30),
+                },
+                124,
+                r#"{"error":"timed_out","program":"npm"}"#,
+            ),
+            (
+                Error::ManagerNotFound(PackageManager::Pnpm),
+                127,
+                r#"{"error":"manager_not_found","manager":"pnpm"}"#,
+            ),
+            (
+                Error::ProgramNotFound("just".to_string()),
+                127,
+                r#"{"error":"program_not_found","program":"just"}"#,
+            ),
+            (
+                Error::CorepackNotFound,
+                127,
+                r#"{"error":"corepack_not_found"}"#,
+            ),
+            (
+                Error::CommandFailed {
+                    program: "yarn".to_string(),
+                    code: 42,
+                },
+                42,
+                r#"{"error":"command_failed","program":"yarn","code":42}"#,
+            ),
+        ]
+    }
+
+    #[test]
+    fn errors_map_to_documented_codes() {
+        for (error, code, _) in documented() {
+            assert_eq!(error.exit_code(), code, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn errors_serialize_to_documented_json() {
+        for (error, _, json) in documented() {
+            assert_eq!(error.to_json(), json);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// `.nignore` holds gitignore-style patterns for directories detection should not look
+// at. Supported are `dir/`, `*` within a segment, `/` anchoring and `!` negation.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::workspace::wildcard_match;
+
+const IGNORE_FILE: &str = ".nignore";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IgnoreFile {
+    // Patterns are relative to the directory the file lives in
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    negated: bool,
+    // Patterns with a `/` match from the base, others match a directory name anywhere
+    anchored: bool,
+    segments: Vec<String>,
+}
+
+impl IgnoreFile {
+    // The closest `.nignore` at or above `dir`
+    pub fn find(dir: &Path) -> Option<IgnoreFile> {
+        dir.ancestors().find_map(|ancestor| {
+            let contents = fs::read_to_string(ancestor.join(IGNORE_FILE)).ok()?;
+            Some(IgnoreFile {
+                base: ancestor.to_path_buf(),
+                rules: contents.lines().filter_map(parse_rule).collect(),
+            })
+        })
+    }
+
+    // A directory is ignored when it or one of its parents below the base is
+    pub fn is_ignored(&self, dir: &Path) -> bool {
+        let Ok(relative) = dir.strip_prefix(&self.base) else {
+            return false;
+        };
+
+        let parts: Vec<String> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+
+        (1..=parts.len()).any(|length| self.matches(&parts[..length]))
+    }
+
+    // The last matching rule decides, like in gitignore
+    fn matches(&self, parts: &[String]) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(parts))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+impl Rule {
+    fn matches(&self, parts: &[String]) -> bool {
+        if self.anchored {
+            return self.segments.len() == parts.len()
+                && self
+                    .segments
+                    .iter()
+                    .zip(parts)
+                    .all(|(segment, part)| wildcard_match(segment, part));
+        }
+
+        parts
+            .last()
+            .is_some_and(|name| wildcard_match(&self.segments[0], name))
+    }
+}
+
+fn parse_rule(line: &str) -> Option<Rule> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negated, pattern) = match line.strip_prefix('!') {
+        Some(pattern) => (true, pattern),
+        None => (false, line),
+    };
+
+    // Only directories are ever checked, so a trailing slash changes nothing
+    let pattern = pattern.trim_end_matches('/');
+    let anchored = pattern.contains('/');
+
+    let segments: Vec<String> = pattern
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if segments.is_empty() {
+        return None;
+    }
+
+    Some(Rule {
+        negated,
+        anchored,
+        segments,
+    })
+}
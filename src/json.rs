@@ -0,0 +1,322 @@
+// A small JSON reader for package.json. Objects keep their key order and
+// numbers keep their original text so documents can be written back unchanged.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object()?
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Object(entries) => Some(entries),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+// Missing values become `null`
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+pub fn parse(input: &str) -> Result<Value, String> {
+    let mut parser = Parser {
+        chars: input.chars().peekable(),
+    };
+
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected `{}` after document", c)),
+    }
+}
+
+// Serializes a value without any whitespace
+pub fn to_string(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(value) => value.clone(),
+        Value::String(value) => quote(value),
+        Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(to_string).collect();
+            format!("[{}]", values.join(","))
+        }
+        Value::Object(entries) => {
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(key, value)| format!("{}:{}", quote(key), to_string(value)))
+                .collect();
+            format!("{{{}}}", entries.join(","))
+        }
+    }
+}
+
+// Formats like npm writes package.json, one entry per line indented with `indent`
+pub fn to_string_pretty(value: &Value, indent: &str) -> String {
+    let mut output = String::new();
+    write_pretty(value, indent, 0, &mut output);
+    output
+}
+
+fn write_pretty(value: &Value, indent: &str, depth: usize, output: &mut String) {
+    let inner = indent.repeat(depth + 1);
+    let outer = indent.repeat(depth);
+
+    match value {
+        Value::Array(values) if !values.is_empty() => {
+            output.push_str("[\n");
+            for (index, value) in values.iter().enumerate() {
+                output.push_str(&inner);
+                write_pretty(value, indent, depth + 1, output);
+                output.push_str(if index + 1 < values.len() {
+                    ",\n"
+                } else {
+                    "\n"
+                });
+            }
+            output.push_str(&outer);
+            output.push(']');
+        }
+        Value::Object(entries) if !entries.is_empty() => {
+            output.push_str("{\n");
+            for (index, (key, value)) in entries.iter().enumerate() {
+                output.push_str(&inner);
+                output.push_str(&quote(key));
+                output.push_str(": ");
+                write_pretty(value, indent, depth + 1, output);
+                output.push_str(if index + 1 < entries.len() {
+                    ",\n"
+                } else {
+                    "\n"
+                });
+            }
+            output.push_str(&outer);
+            output.push('}');
+        }
+        value => output.push_str(&to_string(value)),
+    }
+}
+
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn parse_value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+
+        match self.chars.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => self.parse_string().map(Value::String),
+            Some('t') => self.parse_literal("true", Value::Bool(true)),
+            Some('f') => self.parse_literal("false", Value::Bool(false)),
+            Some('n') => self.parse_literal("null", Value::Null),
+            Some(c) if c.is_ascii_digit() || *c == '-' => Ok(self.parse_number()),
+            Some(c) => Err(format!("unexpected `{}`", c)),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value, String> {
+        self.chars.next();
+        let mut entries = Vec::new();
+
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.chars.next();
+            return Ok(Value::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek() != Some(&'"') {
+                return Err("expected a string key".to_string());
+            }
+            let key = self.parse_string()?;
+
+            self.skip_whitespace();
+            if self.chars.next() != Some(':') {
+                return Err(format!("expected `:` after key `{}`", key));
+            }
+
+            entries.push((key, self.parse_value()?));
+
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(entries)),
+                _ => return Err("expected `,` or `}` in object".to_string()),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, String> {
+        self.chars.next();
+        let mut values = Vec::new();
+
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.chars.next();
+            return Ok(Value::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(values)),
+                _ => return Err("expected `,` or `]` in array".to_string()),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.chars.next();
+        let mut value = String::new();
+
+        loop {
+            match self.chars.next() {
+                None => return Err("unterminated string".to_string()),
+                Some('"') => return Ok(value),
+                Some('\\') => match self.chars.next() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('/') => value.push('/'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('u') => value.push(self.parse_unicode_escape()?),
+                    _ => return Err("invalid escape sequence".to_string()),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.parse_hex4()?;
+
+        // Characters outside the basic plane arrive as a surrogate pair
+        if (0xD800..0xDC00).contains(&high) {
+            if self.chars.next() != Some('\\') || self.chars.next() != Some('u') {
+                return Err("unpaired surrogate in string".to_string());
+            }
+            let low = self.parse_hex4()?;
+            let code = 0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+            return char::from_u32(code).ok_or_else(|| "invalid unicode escape".to_string());
+        }
+
+        char::from_u32(high).ok_or_else(|| "invalid unicode escape".to_string())
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let code: String = (0..4).filter_map(|_| self.chars.next()).collect();
+        u32::from_str_radix(&code, 16).map_err(|_| "invalid unicode escape".to_string())
+    }
+
+    fn parse_number(&mut self) -> Value {
+        let mut number = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+                number.push(c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        Value::Number(number)
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Value) -> Result<Value, String> {
+        for expected in literal.chars() {
+            if self.chars.next() != Some(expected) {
+                return Err(format!("expected `{}`", literal));
+            }
+        }
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+// Diagnostics that only show up with `-v` or `--trace`. Globals rather than parameters
+// because they come from deep inside helpers that otherwise know nothing about options.
+
+use std::fmt::Display;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::json::{self, Value};
+
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+// Where `--trace` events go, None when tracing is off
+static TRACE: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+pub fn verbose(message: impl Display) {
+    if VERBOSE.load(Ordering::Relaxed) {
+        eprintln!("n: {}", message);
+    }
+}
+
+pub fn set_trace(sink: Box<dyn Write + Send>) {
+    *TRACE.lock().unwrap_or_else(|e| e.into_inner()) = Some(sink);
+}
+
+// One JSON line per event, `{"time":1700000000000,"event":"...",...}` with the time in
+// milliseconds since the epoch
+pub fn trace(event: &str, fields: Vec<(&str, Value)>) {
+    let mut sink = TRACE.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(sink) = sink.as_mut() else {
+        return;
+    };
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    let entry = [
+        ("time", Value::Number(time.to_string())),
+        ("event", Value::String(event.to_string())),
+    ]
+    .into_iter()
+    .chain(fields)
+    .map(|(key, value)| (key.to_string(), value))
+    .collect();
+
+    let _ = writeln!(sink, "{}", json::to_string(&Value::Object(entry)));
+    let _ = sink.flush();
+}
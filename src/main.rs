@@ -1,54 +1,1484 @@
+mod cache;
+mod changes;
+mod clear;
+mod cli;
+mod commands;
+mod config;
+mod debounce;
+mod detect;
+mod doctor;
+mod each;
+mod error;
+mod ignore;
+mod json;
+mod log;
+mod outdated;
+mod package_manager;
+mod picker;
+mod pool;
+mod prefix;
+mod recursive;
+mod runner;
+mod scripts;
+mod shell;
+mod tasks;
+#[cfg(test)]
+mod testing;
+mod toml;
+mod watch;
+mod workspace;
+
 use std::env;
-use std::fs;
-use std::process::Command as ProcessCommand;
+use std::fs::File;
+use std::io::{self, IsTerminal, Read};
+use std::path::Path;
+use std::process;
+use std::time::Instant;
+
+use cli::Options;
+use commands::{
+    expand_alias, init_command, invoked_script, is_dlx_candidate, is_install_command,
+    is_remove_command, patch_commands, run_exact_script, split_script_list, take_flag,
+    take_value_flag, try_autocorrect_script,
+};
+use config::Config;
+use detect::resolve_manager;
+use error::Error;
+use package_manager::PackageManager;
+use runner::{
+    confirm_command, ensure_allowed, run_manager, run_program, CommandRunner, CommandSpec,
+    ProcessRunner,
+};
+use scripts::{
+    find_nearest_manifest, find_similar_command, format_script_tree, parse_package_json_scripts,
+    read_package_json_scripts, read_package_json_scripts_full, read_script_default,
+    write_script_default,
+};
+use tasks::{read_tasks, Task};
 
 fn main() {
+    let mut raw_args = env::args();
+    let program = raw_args.next().unwrap_or_default();
+
     // Collecting all arguments except for the first one (which is the program name)
-    let args: Vec<String> = env::args().skip(1).collect();
-    
-    let current_dir = env::current_dir().expect("Failed to read current directory");
+    let args: Vec<String> = raw_args.collect();
+
+    if let Some(dump) = dump_argv(&args) {
+        println!("{}", dump);
+        return;
+    }
+
+    // Symlinked as npx or pnpx everything is a binary to run through dlx
+    let (options, args) = if is_dlx_program(&program) {
+        (Options::default(), [vec!["dlx".to_string()], args].concat())
+    } else {
+        cli::parse_args(&args).unwrap_or_else(|e| exit_with_error(&Error::Usage(e), false))
+    };
+
+    let json = options.json;
+
+    let args = if options.stdin_args {
+        append_stdin_args(args, &mut io::stdin())
+            .unwrap_or_else(|e| exit_with_error(&Error::Usage(e), json))
+    } else {
+        args
+    };
+
+    log::set_verbose(options.verbose);
+    match &options.trace {
+        Some(Some(path)) => match File::create(path) {
+            Ok(file) => log::set_trace(Box::new(file)),
+            Err(e) => exit_with_error(
+                &Error::Other(format!("failed to create {}: {}", path.display(), e)),
+                json,
+            ),
+        },
+        Some(None) => log::set_trace(Box::new(io::stderr())),
+        None => {}
+    }
+    let cwd = env::current_dir().expect("Failed to read current directory");
+    if let Err(error) = track_depth().and_then(|_| run(&ProcessRunner, &cwd, options, args)) {
+        exit_with_error(&error, json);
+    }
+}
+
+// Split on whitespace and quotes only, so other tools don't have to quote for a shell
+fn append_stdin_args(args: Vec<String>, input: &mut dyn Read) -> Result<Vec<String>, String> {
+    let mut contents = String::new();
+    input
+        .read_to_string(&mut contents)
+        .map_err(|e| format!("failed to read arguments from stdin: {}", e))?;
+
+    Ok([args, shell::split(&contents)?].concat())
+}
 
-    match detect_package_manager(&current_dir) {
-        Some(manager) => run_command(&manager, &args),
-        None => println!("No package manager detected."),
+// The script names of a package.json piped in with `--scripts-from-stdin`
+fn read_scripts_from(mut input: impl Read) -> Result<Vec<String>, Error> {
+    let mut contents = String::new();
+    input
+        .read_to_string(&mut contents)
+        .map_err(|e| Error::Other(format!("failed to read package.json from stdin: {}", e)))?;
+
+    parse_package_json_scripts(&contents).map_err(Error::Usage)
+}
+
+// Hidden, shows exactly what a wrapping tool handed over before anything is parsed
+fn dump_argv(args: &[String]) -> Option<String> {
+    if args.first().is_none_or(|arg| arg != "--dump-argv") {
+        return None;
     }
+
+    let argv = args.iter().cloned().map(json::Value::String).collect();
+    Some(json::to_string(&json::Value::Array(argv)))
 }
 
-fn detect_package_manager(dir: &std::path::Path) -> Option<String> {
-    let entries = fs::read_dir(dir).expect("Failed to read directory entries");
-    
-    for entry in entries {
-        let entry = entry.expect("Failed to read entry");
-        let path = entry.path();
-    
-    if path.is_file() {
-            if path.ends_with("package-lock.json") {
-                return Some("npm".to_string());
+fn run(
+    runner: &dyn CommandRunner,
+    cwd: &Path,
+    options: Options,
+    args: Vec<String>,
+) -> Result<(), Error> {
+    if args.first().is_some_and(|arg| arg == "reset-cache") {
+        let (files, bytes) = cache::reset_cache().map_err(Error::Other)?;
+        println!("Removed {} cache entries ({} bytes)", files, bytes);
+        return Ok(());
+    }
+
+    // n's own settings, anything but get and set still goes to the manager's `config`.
+    // Handled before the config is loaded, so a broken file can still be repaired.
+    if args.first().is_some_and(|arg| arg == "config") {
+        match (
+            args.get(1).map(String::as_str),
+            args.get(2..).unwrap_or_default(),
+        ) {
+            (Some("get"), [key]) => {
+                let value = config::get_user_setting(key).map_err(Error::Config)?;
+                match value {
+                    toml::Value::String(value) => println!("{}", value),
+                    value => println!("{}", toml::format_value(&value)),
+                }
+                return Ok(());
             }
-            
-            if path.ends_with("yarn.lock") {
-                return Some("yarn".to_string());
+            (Some("set"), [key, value]) => {
+                let path = config::set_user_setting(key, value).map_err(Error::Config)?;
+                println!("Set `{}` in {}", key, path.display());
+                return Ok(());
             }
+            _ => {}
+        }
+    }
+
+    // An explicit manifest moves everything to the directory it lives in. Otherwise scripts
+    // come from the nearest package.json, while the manager may be detected further up.
+    let (manifest, current_dir) = match &options.package_json {
+        Some(path) => {
+            let manifest = cwd.join(path);
+            let dir = manifest
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| cwd.to_path_buf());
+            (manifest, dir)
+        }
+        None => (find_nearest_manifest(cwd), cwd.to_path_buf()),
+    };
+
+    let config = Config::load(&current_dir, options.profile.as_deref()).map_err(Error::Config)?;
+
+    if options.dump_config {
+        print!("{}", config.dump());
+        return Ok(());
+    }
+
+    let tasks = if config.task_files {
+        read_tasks(&current_dir)
+    } else {
+        Vec::new()
+    };
+
+    // Generated manifests can be piped in instead of living next to the project
+    let scripts = if options.scripts_from_stdin {
+        read_scripts_from(io::stdin())?
+    } else {
+        read_package_json_scripts(&manifest)
+    };
+
+    if args.first().is_some_and(|arg| arg == "set-script-default") {
+        let Some(script) = args.get(1) else {
+            return Err(Error::Usage(
+                "`set-script-default` needs a script name".to_string(),
+            ));
+        };
+
+        write_script_default(&manifest, script).map_err(Error::Other)?;
+        println!("A bare `n` now runs `{}`", script);
+        return Ok(());
+    }
+
+    // Anything after `--pick` is passed on to the picked script
+    let args = if options.pick {
+        let script = picker::pick_script(&scripts)?;
+        [vec!["run".to_string(), script], args].concat()
+    } else {
+        args
+    };
+
+    // A bare `n` runs the script picked with `set-script-default`
+    let args = match read_script_default(&manifest) {
+        Some(script) if args.is_empty() => vec!["run".to_string(), script],
+        _ => args,
+    };
+
+    // A bare manager would only print its help, or fail in its own way
+    let runs_command = !options.print_manager && !options.watch_detect;
+    if runs_command && args.is_empty() && tasks.is_empty() && scripts.is_empty() {
+        return Err(Error::NoScripts);
+    }
+
+    if args.first().is_some_and(|arg| arg == "ls-scripts") {
+        if options.json || args.iter().any(|arg| arg == "--json") {
+            println!("{}", scripts_json(&manifest));
+            return Ok(());
+        }
 
-            if path.ends_with("bun.lockb") {
-                return Some("bun".to_string());
+        if args.iter().any(|arg| arg == "--tree") {
+            print!("{}", format_script_tree(&scripts));
+        } else {
+            for script in &scripts {
+                println!("{}", script);
             }
+        }
+        for task in &tasks {
+            println!("{} ({})", task.name, task.runner.program());
+        }
+        return Ok(());
+    }
+
+    if args.first().is_some_and(|arg| arg == "which") {
+        println!("{}", which_script(&manifest, &args[1..], &config)?);
+        return Ok(());
+    }
 
-            if path.ends_with("pnpm-lock.yaml") {
-                return Some("pnpm".to_string());
+    // Checked where the manager is detected, so stale lockfiles next to the real one show up
+    if args.first().is_some_and(|arg| arg == "doctor") {
+        let root = resolve_manager(&current_dir, &config, options.manager)
+            .map(|detection| detection.root)
+            .unwrap_or_else(|| current_dir.clone());
+
+        let fix = args.iter().any(|arg| arg == "--fix");
+        return doctor::run_doctor(runner, &root, &config, fix, options.yes);
+    }
+
+    // Meant for shell prompts: just the name, or nothing and a failing exit code
+    if options.print_manager {
+        match manager_line(&current_dir, &config, options.manager) {
+            Some(line) => {
+                print!("{}", line);
+                return Ok(());
             }
+            None => return Err(Error::NothingToPrint),
+        }
+    }
+
+    if options.watch_detect {
+        watch::watch_detection(&current_dir, &config, |detection| {
+            match detection {
+                Some(detection) => println!("{} ({})", detection.manager, detection.source),
+                None => println!("none"),
+            }
+            true
+        });
+        return Ok(());
+    }
+
+    // Pinning through corepack works the same whatever manager is detected
+    if args.first().is_some_and(|arg| arg == "use") {
+        let corepack_args = args.clone();
+
+        if options.print_command {
+            println!("{}", shell::command_line("corepack", &corepack_args));
+            return Ok(());
+        }
+
+        return run_program(
+            runner,
+            "corepack",
+            &corepack_args,
+            &current_dir,
+            options.timeout,
+        )
+        .map_err(|error| match error {
+            Error::ProgramNotFound(_) => Error::CorepackNotFound,
+            error => error,
+        });
+    }
+
+    // `install --each <glob>` detects every matched project on its own
+    if args
+        .first()
+        .is_some_and(|arg| matches!(arg.as_str(), "i" | "install" | "ci"))
+    {
+        if let (Some(pattern), install_args) = take_value_flag(&args, "--each") {
+            return each::install_each(runner, cwd, &pattern, &install_args, &options);
         }
     }
-    None
+
+    let Some(detection) = resolve_manager(&current_dir, &config, options.manager) else {
+        let reason = detect::explain_not_found(&current_dir, &config);
+
+        // Nothing to detect in a project that doesn't exist yet, so ask which one to start
+        if args.first().is_some_and(|arg| arg == "init") && io::stdin().is_terminal() {
+            eprintln!("n: {}", reason);
+            let input = &mut io::stdin().lock();
+            return init_project(runner, input, &options, &config, &args[1..], &current_dir);
+        }
+        if detect::is_published_package(&current_dir) {
+            eprintln!(
+                "n: this looks like an extracted published package, pick a manager with --manager or N_PACKAGE_MANAGER"
+            );
+        }
+        log::verbose(&reason);
+        return Err(Error::NoManagerDetected);
+    };
+    let manager = detection.manager;
+
+    // One report for the whole workspace, keyed by package
+    let is_outdated_json = args.first().is_some_and(|arg| arg == "outdated")
+        && (options.json || args.iter().any(|arg| arg == "--json"));
+    let prints = options.print_command || options.print;
+    if is_outdated_json && !prints && !workspace::enumerate_workspaces(&detection.root).is_empty() {
+        let (_, mut outdated_args) = take_flag(&args, "--json");
+        outdated_args.insert(1, "--json".to_string());
+        return outdated::outdated_json(
+            runner,
+            manager,
+            &detection.root,
+            &outdated_args,
+            &config,
+            options.timeout,
+        );
+    }
+
+    // Everything goes at once, from the nearest package.json
+    if args.first().is_some_and(|arg| is_remove_command(arg)) {
+        if let (true, rest) = take_flag(&args[1..], "--all") {
+            let (reinstall, rest) = take_flag(&rest, "--reinstall");
+            return clear::remove_all(
+                runner, manager, &manifest, &rest, reinstall, &config, &options,
+            );
+        }
+    }
+
+    if options.recursive {
+        let script_args = match args.first() {
+            Some(first) if first == "run" => &args[1..],
+            _ => &args[..],
+        };
+        let Some((script, rest)) = script_args.split_first() else {
+            return Err(Error::Usage(
+                "`--recursive` needs a script to run".to_string(),
+            ));
+        };
+
+        return recursive::run_recursive(
+            runner,
+            manager,
+            &detection.root,
+            script,
+            rest,
+            &config,
+            &options,
+        );
+    }
+
+    // Tasks from a justfile or Taskfile are autocorrect candidates just like scripts
+    let candidates: Vec<String> = scripts
+        .iter()
+        .cloned()
+        .chain(tasks.iter().map(|task| task.name.clone()))
+        .collect();
+
+    // Only the last of a burst of identical invocations gets past this
+    if let Some(after) = options.after {
+        if !debounce::settle(&current_dir, &args, after).map_err(Error::Other)? {
+            return Ok(());
+        }
+    }
+
+    // Comma separated scripts run one after another and stop at the first failure
+    let invocations = split_script_list(&args);
+    let prefix_output = invocations.len() > 1;
+
+    for invocation in invocations {
+        // `run --if-present` skips missing scripts instead of failing or autocorrecting them
+        let (if_present, invocation) = match invocation.first() {
+            Some(command) if command == "run" => take_flag(&invocation, "--if-present"),
+            _ => (false, invocation),
+        };
+
+        if if_present {
+            let script = invocation[1..].iter().find(|arg| !arg.starts_with('-'));
+            let exists = script.is_some_and(|script| candidates.contains(script));
+
+            if !exists {
+                log::verbose(format_args!(
+                    "no script `{}`, skipping it",
+                    script.map(String::as_str).unwrap_or_default()
+                ));
+                continue;
+            }
+        }
+
+        let invocation = if config.autocorrect {
+            try_autocorrect_script(manager, &invocation, &candidates, &config)?
+        } else {
+            invocation
+        };
+
+        if let Some((task, task_args)) = find_task(&invocation, &scripts, &tasks) {
+            let program = task.runner.program();
+
+            if options.print {
+                println!("{}", shell::argv_json(program, &task_args));
+                continue;
+            }
+            if options.print_command {
+                println!("{}", shell::command_line(program, &task_args));
+                continue;
+            }
+
+            run_program(runner, program, &task_args, &current_dir, options.timeout)?;
+            continue;
+        }
+
+        let invocation = if options.dlx_fallback
+            && is_dlx_candidate(manager, &invocation, &candidates, &config)
+        {
+            eprintln!(
+                "n: `{}` is neither a command nor a script, running it with dlx",
+                invocation[0]
+            );
+            [vec!["dlx".to_string()], invocation].concat()
+        } else {
+            run_exact_script(manager, &invocation, &scripts, &config)
+        };
+
+        // `remove --prune-refs` warns about siblings still depending on what was removed
+        let (prune_refs, invocation) = match invocation.first() {
+            Some(command) if is_remove_command(command) => take_flag(&invocation, "--prune-refs"),
+            _ => (false, invocation),
+        };
+
+        if let Some(alias) = &config.dev_alias {
+            if invocation.first().is_some_and(|arg| arg == "d") && !scripts.contains(&alias.script)
+            {
+                eprintln!(
+                    "n: `dev_alias` points at `{}`, which is not a script",
+                    alias.script
+                );
+            }
+        }
+
+        let patched = patch_commands(manager, &invocation, &config);
+        ensure_allowed(&invocation, &patched, &config.deny)?;
+
+        // Manager-wide flags like pnpm's `--dir` have to come before the command
+        let argv = [options.manager_args.clone(), patched.clone()].concat();
+
+        // An explicit version goes through corepack, whatever the project pins
+        let (program, argv) = match &options.manager_version {
+            Some(spec) => ("corepack", [vec![spec.clone()], argv].concat()),
+            None => (config.binary(manager), argv),
+        };
+
+        // Project-wide commands from `root_commands` always run at the root. The command as
+        // typed counts too, a frozen `install` turns into npm's `ci`.
+        let runs_at_root = options.at_root
+            || [
+                invocation.first().map(|command| expand_alias(command)),
+                patched.first().map(String::as_str),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|command| config.root_commands.iter().any(|listed| listed == command));
+        let run_dir = if runs_at_root {
+            &detection.root
+        } else {
+            &current_dir
+        };
+
+        if prints {
+            trace_spawn(program, &argv, run_dir, true);
+        }
+        if options.print {
+            println!("{}", shell::argv_json(program, &argv));
+            continue;
+        }
+        if options.print_command {
+            println!("{}", shell::command_line(program, &argv));
+            continue;
+        }
+
+        if !options.yes && !confirm_command(&patched, program, &argv, &config.confirm) {
+            return Err(Error::Aborted);
+        }
+
+        let spinner = options.spinner
+            && patched
+                .first()
+                .is_some_and(|command| is_install_command(command));
+
+        let spec = CommandSpec {
+            program: program.to_string(),
+            args: argv,
+            dir: run_dir.clone(),
+            timeout: options.timeout,
+            spinner,
+            env: node_env(&config, &invocation),
+            prefix: invoked_script(&invocation)
+                .filter(|_| prefix_output)
+                .map(str::to_string),
+            bail_on: options
+                .bail_on_warning
+                .then(|| config.warning_marker.clone()),
+        };
+
+        trace_spawn(program, &spec.args, run_dir, false);
+        let started = Instant::now();
+        let result = run_manager(runner, manager, &spec);
+        trace_exit(program, &result, started);
+        result?;
+
+        if prune_refs {
+            warn_orphaned_references(&detection.root, &invocation[1..]);
+        }
+    }
+
+    Ok(())
+}
+
+fn init_project(
+    runner: &dyn CommandRunner,
+    input: &mut dyn io::BufRead,
+    options: &Options,
+    config: &Config,
+    rest: &[String],
+    dir: &Path,
+) -> Result<(), Error> {
+    let manager = picker::pick_manager(input)?;
+    let program = config.binary(manager);
+    let argv = [options.manager_args.clone(), init_command(manager, rest)].concat();
+
+    if options.print {
+        println!("{}", shell::argv_json(program, &argv));
+        return Ok(());
+    }
+    if options.print_command {
+        println!("{}", shell::command_line(program, &argv));
+        return Ok(());
+    }
+
+    let spec = CommandSpec {
+        program: program.to_string(),
+        args: argv,
+        dir: dir.to_path_buf(),
+        timeout: options.timeout,
+        spinner: false,
+        env: Vec::new(),
+        prefix: None,
+        bail_on: None,
+    };
+    run_manager(runner, manager, &spec)
 }
 
-fn run_command(manager: &str, args: &[String]) {
-    let status = ProcessCommand::new(manager)
-        .args(args)
-        .status()
-        .expect("Failed to execute command");
+// `[{"name":"build","command":"tsc -p ."}]`, sorted by name for editors
+fn scripts_json(manifest: &Path) -> String {
+    let mut scripts = read_package_json_scripts_full(manifest);
+    scripts.sort();
+
+    let entries = scripts
+        .into_iter()
+        .map(|(name, command)| {
+            json::Value::Object(vec![
+                ("name".to_string(), json::Value::String(name)),
+                ("command".to_string(), json::Value::String(command)),
+            ])
+        })
+        .collect();
+
+    json::to_string(&json::Value::Array(entries))
+}
+
+fn trace_spawn(program: &str, args: &[String], dir: &Path, dry_run: bool) {
+    log::trace(
+        "command_spawned",
+        vec![
+            ("program", program.into()),
+            (
+                "args",
+                json::Value::Array(args.iter().map(|arg| arg.as_str().into()).collect()),
+            ),
+            ("dir", dir.display().to_string().into()),
+            ("dry_run", dry_run.into()),
+        ],
+    );
+}
+
+// The code is null when the command never got to exit on its own
+fn trace_exit(program: &str, result: &Result<(), Error>, started: Instant) {
+    let code = match result {
+        Ok(()) => Some(0),
+        Err(Error::CommandFailed { code, .. }) => Some(*code),
+        Err(_) => None,
+    };
+
+    log::trace(
+        "command_exited",
+        vec![
+            ("program", program.into()),
+            (
+                "code",
+                code.map_or(json::Value::Null, |code| {
+                    json::Value::Number(code.to_string())
+                }),
+            ),
+            (
+                "duration_ms",
+                json::Value::Number(started.elapsed().as_millis().to_string()),
+            ),
+        ],
+    );
+}
+
+fn warn_orphaned_references(root: &Path, removed: &[String]) {
+    let dependencies = removed.iter().filter(|arg| !arg.starts_with('-'));
+
+    for dependency in dependencies {
+        for package in workspace::find_references(root, dependency) {
+            eprintln!(
+                "n: {} still depends on {} ({})",
+                package.name,
+                dependency,
+                package.dir.display()
+            );
+        }
+    }
+}
+
+// What a script actually runs, after the same alias and autocorrect steps as running it
+fn which_script(manifest: &Path, args: &[String], config: &Config) -> Result<String, Error> {
+    let Some(name) = invoked_script(args) else {
+        return Err(Error::Usage("`which` needs a script name".to_string()));
+    };
+    let name = match &config.dev_alias {
+        Some(alias) if args[0] == "d" => alias.script.as_str(),
+        _ => name,
+    };
+
+    let scripts = read_package_json_scripts_full(manifest);
+    let names: Vec<String> = scripts.iter().map(|(name, _)| name.clone()).collect();
+
+    let resolved = if names.iter().any(|script| script == name) {
+        Some(name.to_string())
+    } else if config.autocorrect {
+        let similar = find_similar_command(name, &names);
+        if let Some(script) = &similar {
+            eprintln!("n: no script `{}`, showing `{}` instead", name, script);
+        }
+        similar
+    } else {
+        None
+    };
+
+    resolved
+        .and_then(|script| scripts.into_iter().find(|(name, _)| *name == script))
+        .map(|(_, command)| command)
+        .ok_or_else(|| Error::Other(format!("no script `{}`", name)))
+}
+
+// What `--print-manager` prints, nothing when no manager is detected
+fn manager_line(dir: &Path, config: &Config, forced: Option<PackageManager>) -> Option<String> {
+    resolve_manager(dir, config, forced).map(|detection| format!("{}\n", detection.manager))
+}
+
+// `NODE_ENV` for the script about to run when `set_node_env` is on, an inherited
+// `NODE_ENV` always wins
+fn node_env(config: &Config, invocation: &[String]) -> Vec<(String, String)> {
+    if !config.set_node_env || env::var_os("NODE_ENV").is_some() {
+        return Vec::new();
+    }
+
+    invoked_script(invocation)
+        .and_then(|script| config.node_env.get(script))
+        .map(|value| vec![("NODE_ENV".to_string(), value.clone())])
+        .unwrap_or_default()
+}
+
+// Package scripts win over tasks of the same name
+fn find_task<'t>(
+    invocation: &[String],
+    scripts: &[String],
+    tasks: &'t [Task],
+) -> Option<(&'t Task, Vec<String>)> {
+    let position = if invocation.first().is_some_and(|arg| arg == "run") {
+        1
+    } else {
+        0
+    };
+    let name = invocation.get(position)?;
+
+    if scripts.contains(name) {
+        return None;
+    }
+
+    tasks
+        .iter()
+        .find(|task| task.name == *name)
+        .map(|task| (task, invocation[position..].to_vec()))
+}
+
+// Scripts calling n again are fine, a chain this deep almost certainly loops
+const MAX_DEPTH: usize = 10;
+
+// Every n passes `N_DEPTH` one higher to whatever it spawns
+fn track_depth() -> Result<(), Error> {
+    let depth = check_depth(env::var("N_DEPTH").ok().as_deref())?;
+    env::set_var("N_DEPTH", (depth + 1).to_string());
+    Ok(())
+}
+
+// The depth `N_DEPTH` says this n runs at, an error when that is too deep
+fn check_depth(value: Option<&str>) -> Result<usize, Error> {
+    let depth: usize = value.and_then(|depth| depth.parse().ok()).unwrap_or(0);
+
+    if depth >= MAX_DEPTH {
+        return Err(Error::Other(format!(
+            "n is nested {} levels deep (N_DEPTH), a script is probably calling n in a loop",
+            depth
+        )));
+    }
+
+    Ok(depth)
+}
+
+fn is_dlx_program(program: &str) -> bool {
+    Path::new(program)
+        .file_stem()
+        .is_some_and(|name| name == "npx" || name == "pnpx")
+}
+
+fn exit_with_error(error: &Error, json: bool) -> ! {
+    if json {
+        eprintln!("{}", error.to_json());
+    } else {
+        match error {
+            // The manager already explained what went wrong
+            Error::CommandFailed { .. } => eprintln!("{}", error),
+            Error::NothingToPrint => {}
+            _ => eprintln!("n: {}", error),
+        }
+    }
+
+    process::exit(error.exit_code());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::RecordingRunner;
+    use crate::testing::TempDir;
+    use std::path::PathBuf;
+
+    fn npm_project() -> TempDir {
+        let dir = TempDir::new();
+        dir.write("package.json", r#"{"scripts":{"build":"tsc"}}"#);
+        dir.write("package-lock.json", "{}");
+        dir
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn b_runs_the_build_script_on_npm() {
+        let dir = npm_project();
+        let runner = RecordingRunner::default();
+
+        run(&runner, dir.path(), Options::default(), args(&["b"])).unwrap();
+
+        assert_eq!(runner.argvs(), vec![args(&["npm", "run", "build"])]);
+        assert_eq!(runner.commands()[0].dir, dir.path());
+    }
+    #[test]
+    fn passes_on_the_exit_code_of_a_failing_script() {
+        let dir = npm_project();
+        let runner = RecordingRunner::failing_with(&[3]);
+
+        let result = run(&runner, dir.path(), Options::default(), args(&["b"]));
+
+        assert_eq!(result.map_err(|error| error.exit_code()), Err(3));
+    }
+
+    #[test]
+    fn profile_install_flags_apply_to_the_invocation() {
+        let dir = npm_project();
+        dir.write(
+            ".nrc.toml",
+            "[profiles.ci]\ninstall_flags = [\"--no-audit\"]\n",
+        );
+        let runner = RecordingRunner::default();
+        let options = Options {
+            profile: Some("ci".to_string()),
+            ..Options::default()
+        };
+
+        run(&runner, dir.path(), options, args(&["add", "lodash"])).unwrap();
+        run(
+            &runner,
+            dir.path(),
+            Options::default(),
+            args(&["add", "lodash"]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            runner.argvs(),
+            [
+                args(&["npm", "install", "lodash", "--no-audit"]),
+                args(&["npm", "install", "lodash"]),
+            ]
+        );
+    }
+
+    // An npm lockfile at the root and the package.json scripts run from in `packages/app`
+    fn nested_project() -> (TempDir, PathBuf) {
+        let dir = npm_project();
+        let app = dir.write(
+            "packages/app/package.json",
+            r#"{"scripts":{"build":"vite build"}}"#,
+        );
+        let app = app.parent().unwrap().to_path_buf();
+        (dir, app)
+    }
+
+    #[test]
+    fn at_root_runs_in_the_detected_root() {
+        let (dir, app) = nested_project();
+        let runner = RecordingRunner::default();
+        let options = Options {
+            at_root: true,
+            ..Options::default()
+        };
+
+        run(&runner, &app, options, args(&["build"])).unwrap();
+
+        assert_eq!(runner.commands()[0].dir, dir.path());
+    }
+
+    #[test]
+    fn commands_run_in_the_cwd_by_default() {
+        let (_dir, app) = nested_project();
+        let runner = RecordingRunner::default();
+
+        run(&runner, &app, Options::default(), args(&["build"])).unwrap();
+
+        assert_eq!(runner.commands()[0].dir, app);
+    }
+
+    #[test]
+    fn script_list_runs_in_order_and_stops_at_a_failure() {
+        let dir = npm_project();
+        dir.write(
+            "package.json",
+            r#"{"scripts":{"build":"tsc","test":"jest","lint":"eslint"}}"#,
+        );
+        let runner = RecordingRunner::failing_with(&[0, 1]);
+
+        let result = run(
+            &runner,
+            dir.path(),
+            Options::default(),
+            args(&["run", "build,test,lint"]),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(
+            runner.argvs(),
+            [
+                args(&["npm", "run", "build"]),
+                args(&["npm", "run", "test"])
+            ]
+        );
+    }
+
+    #[test]
+    fn npx_and_pnpx_symlinks_are_dlx() {
+        assert!(is_dlx_program("/usr/local/bin/npx"));
+        assert!(is_dlx_program("pnpx"));
+        assert!(!is_dlx_program("/usr/local/bin/n"));
+    }
+
+    #[test]
+    fn explicit_manifest_replaces_the_discovered_one() {
+        let dir = npm_project();
+        dir.write("tools/manifest.json", r#"{"scripts":{"lint":"eslint ."}}"#);
+        let runner = RecordingRunner::default();
+        let options = Options {
+            package_json: Some(PathBuf::from("tools/manifest.json")),
+            ..Options::default()
+        };
+
+        run(&runner, dir.path(), options, args(&["lint"])).unwrap();
+
+        assert_eq!(runner.argvs(), [args(&["npm", "run", "lint"])]);
+        assert_eq!(runner.commands()[0].dir, dir.path().join("tools"));
+    }
+
+    #[test]
+    fn use_pins_through_corepack() {
+        let dir = npm_project();
+        let runner = RecordingRunner::default();
+
+        run(
+            &runner,
+            dir.path(),
+            Options::default(),
+            args(&["use", "pnpm@9"]),
+        )
+        .unwrap();
+
+        assert_eq!(runner.argvs(), [args(&["corepack", "use", "pnpm@9"])]);
+    }
+
+    #[test]
+    fn use_reports_a_missing_corepack() {
+        let dir = npm_project();
+        let runner = RecordingRunner {
+            missing: vec!["corepack".to_string()],
+            ..RecordingRunner::default()
+        };
+
+        let result = run(
+            &runner,
+            dir.path(),
+            Options::default(),
+            args(&["use", "pnpm@9"]),
+        );
+
+        assert_eq!(result, Err(Error::CorepackNotFound));
+    }
+
+    #[test]
+    fn scripts_json_lists_names_and_commands() {
+        let dir = TempDir::new();
+        let manifest = dir.write(
+            "package.json",
+            r#"{"scripts":{"test":"jest --ci","build":"tsc -p \"tsconfig.json\""}}"#,
+        );
+
+        assert_eq!(
+            scripts_json(&manifest),
+            r#"[{"name":"build","command":"tsc -p \"tsconfig.json\""},{"name":"test","command":"jest --ci"}]"#
+        );
+    }
+
+    #[test]
+    fn root_commands_run_from_the_root_and_scripts_from_the_cwd() {
+        let (dir, app) = nested_project();
+        dir.write(".nrc.toml", "root_commands = [\"install\"]\n");
+        let runner = RecordingRunner::default();
+
+        run(&runner, &app, Options::default(), args(&["i", "--frozen"])).unwrap();
+        run(&runner, &app, Options::default(), args(&["build"])).unwrap();
+
+        let dirs: Vec<PathBuf> = runner.commands().into_iter().map(|spec| spec.dir).collect();
+        assert_eq!(dirs, [dir.path().to_path_buf(), app]);
+    }
+
+    #[test]
+    fn configured_binary_replaces_the_manager() {
+        let dir = TempDir::new();
+        dir.write("package.json", r#"{"scripts":{"build":"tsc"}}"#);
+        dir.write("pnpm-lock.yaml", "");
+        dir.write(".nrc.toml", "binaries = { pnpm = \"corp-pnpm\" }\n");
+        let runner = RecordingRunner::default();
+
+        run(&runner, dir.path(), Options::default(), args(&["b"])).unwrap();
+
+        assert_eq!(runner.argvs(), [args(&["corp-pnpm", "run", "build"])]);
+    }
+
+    #[test]
+    fn if_present_runs_an_existing_script() {
+        let dir = npm_project();
+        let runner = RecordingRunner::default();
+
+        run(
+            &runner,
+            dir.path(),
+            Options::default(),
+            args(&["run", "--if-present", "build"]),
+        )
+        .unwrap();
+
+        assert_eq!(runner.argvs(), [args(&["npm", "run", "build"])]);
+    }
+
+    #[test]
+    fn if_present_skips_a_missing_script() {
+        let dir = npm_project();
+        let runner = RecordingRunner::default();
+
+        let result = run(
+            &runner,
+            dir.path(),
+            Options::default(),
+            args(&["run", "--if-present", "biuld"]),
+        );
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(runner.argvs(), Vec::<Vec<String>>::new());
+    }
+
+    #[test]
+    fn dump_argv_matches_the_raw_arguments() {
+        let raw = args(&[
+            "--dump-argv",
+            "-C",
+            "my app",
+            "run",
+            "--flag=\"x\"",
+            "--",
+            "a b",
+        ]);
+        let dumped = json::parse(&dump_argv(&raw).unwrap()).unwrap();
+
+        let dumped: Vec<&str> = dumped
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|arg| arg.as_str().unwrap())
+            .collect();
+        assert_eq!(dumped, raw);
+        assert_eq!(dump_argv(&args(&["run", "--dump-argv"])), None);
+    }
+
+    #[test]
+    fn set_node_env_reaches_the_dev_script() {
+        let dir = TempDir::new();
+        dir.write("package.json", r#"{"scripts":{"dev":"vite"}}"#);
+        dir.write("package-lock.json", "{}");
+        let runner = RecordingRunner::default();
+
+        run(&runner, dir.path(), Options::default(), args(&["d"])).unwrap();
+        dir.write(".nrc.toml", "set_node_env = true\n");
+        run(&runner, dir.path(), Options::default(), args(&["d"])).unwrap();
+
+        let envs: Vec<_> = runner.commands().into_iter().map(|spec| spec.env).collect();
+        assert_eq!(
+            envs,
+            [
+                vec![],
+                vec![("NODE_ENV".to_string(), "development".to_string())],
+            ]
+        );
+    }
+
+    #[test]
+    fn print_manager_is_just_the_name() {
+        let dir = TempDir::new();
+        let config = Config::default();
+        assert_eq!(manager_line(dir.path(), &config, None), None);
+
+        dir.write("pnpm-lock.yaml", "");
+        assert_eq!(
+            manager_line(dir.path(), &config, None),
+            Some("pnpm\n".to_string())
+        );
+    }
+
+    #[test]
+    fn print_manager_without_a_manager_fails_quietly() {
+        let dir = TempDir::new();
+        let runner = RecordingRunner::default();
+        let options = || Options {
+            print_manager: true,
+            ..Options::default()
+        };
+
+        let result = run(&runner, dir.path(), options(), Vec::new());
+        assert_eq!(result, Err(Error::NothingToPrint));
+        assert_eq!(Error::NothingToPrint.exit_code(), 1);
+
+        dir.write("pnpm-lock.yaml", "");
+        assert_eq!(run(&runner, dir.path(), options(), Vec::new()), Ok(()));
+        assert!(runner.commands().is_empty());
+    }
+
+    #[test]
+    fn denied_commands_abort_before_anything_runs() {
+        let dir = npm_project();
+        dir.write(".nrc.toml", "deny = [\"publish\", \"uninstall\"]\n");
+        let runner = RecordingRunner::default();
+        let dry_run = || Options {
+            print_command: true,
+            ..Options::default()
+        };
+
+        let denied = run(&runner, dir.path(), dry_run(), args(&["publish"]));
+        assert_eq!(denied, Err(Error::Denied("publish".to_string())));
+        let denied = run(
+            &runner,
+            dir.path(),
+            Options::default(),
+            args(&["rm", "lodash"]),
+        );
+        assert_eq!(denied, Err(Error::Denied("rm".to_string())));
+
+        assert_eq!(run(&runner, dir.path(), dry_run(), args(&["b"])), Ok(()));
+        assert!(runner.commands().is_empty());
+    }
+
+    #[test]
+    fn deny_at_the_root_applies_in_subdirectories() {
+        let (dir, app) = nested_project();
+        dir.write(".nrc.toml", "deny = [\"publish\"]\n");
+        let runner = RecordingRunner::default();
+
+        let denied = run(&runner, &app, Options::default(), args(&["publish"]));
+        assert_eq!(denied, Err(Error::Denied("publish".to_string())));
+        assert!(runner.commands().is_empty());
+    }
+
+    #[test]
+    fn deny_matches_the_command_bun_translates() {
+        let dir = TempDir::new();
+        dir.write("package.json", r#"{"scripts":{}}"#);
+        dir.write("bun.lockb", "");
+        dir.write(".nrc.toml", "deny = [\"exec\"]\n");
+        let runner = RecordingRunner::default();
+
+        let denied = run(
+            &runner,
+            dir.path(),
+            Options::default(),
+            args(&["exec", "vite"]),
+        );
+        assert_eq!(denied, Err(Error::Denied("exec".to_string())));
+        assert!(runner.commands().is_empty());
+    }
+
+    #[test]
+    fn manager_comes_from_the_root_and_scripts_from_the_subpackage() {
+        let dir = TempDir::new();
+        dir.write("package.json", r#"{"scripts":{"lint":"eslint ."}}"#);
+        dir.write("pnpm-lock.yaml", "");
+        let app = dir.write(
+            "packages/app/package.json",
+            r#"{"scripts":{"typecheck":"tsc"}}"#,
+        );
+        let app = app.parent().unwrap();
+        let runner = RecordingRunner::default();
+
+        run(&runner, app, Options::default(), args(&["typechek"])).unwrap();
+
+        assert_eq!(runner.argvs(), [args(&["pnpm", "run", "typecheck"])]);
+        assert_eq!(runner.commands()[0].dir, app);
+    }
+
+    #[test]
+    fn manager_args_come_before_the_command() {
+        let dir = npm_project();
+        let runner = RecordingRunner::default();
+        let (options, rest) =
+            cli::parse_args(&args(&["--manager-args", "--loglevel=warn", "install"])).unwrap();
+
+        run(&runner, dir.path(), options, rest).unwrap();
+
+        assert_eq!(
+            runner.argvs(),
+            [args(&["npm", "--loglevel=warn", "install"])]
+        );
+    }
+
+    #[test]
+    fn deep_nesting_aborts_with_an_explanation() {
+        assert_eq!(check_depth(None), Ok(0));
+        assert_eq!(check_depth(Some("3")), Ok(3));
+
+        let error = check_depth(Some("12")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "n is nested 12 levels deep (N_DEPTH), a script is probably calling n in a loop"
+        );
+        assert_ne!(error.exit_code(), 0);
+    }
+
+    #[test]
+    fn stdin_args_run_like_typed_ones() {
+        let dir = npm_project();
+        let runner = RecordingRunner::default();
+
+        let stdin_args = append_stdin_args(Vec::new(), &mut "install lodash\n".as_bytes());
+        run(&runner, dir.path(), Options::default(), stdin_args.unwrap()).unwrap();
+
+        assert_eq!(runner.argvs(), [args(&["npm", "install", "lodash"])]);
+        assert_eq!(
+            append_stdin_args(args(&["add"]), &mut "'my pkg'\n  -D".as_bytes()),
+            Ok(args(&["add", "my pkg", "-D"]))
+        );
+    }
+
+    #[test]
+    fn published_package_is_not_installed_into() {
+        let dir = TempDir::new();
+        dir.write(
+            "package.json",
+            r#"{"name":"cli-tool","bin":{"cli-tool":"bin/cli.js"},"scripts":{"build":"tsc"}}"#,
+        );
+        let runner = RecordingRunner::default();
+        assert!(detect::is_published_package(dir.path()));
+
+        for invocation in [&["build"][..], &["install"], &[]] {
+            let result = run(&runner, dir.path(), Options::default(), args(invocation));
+            assert_eq!(result, Err(Error::NoManagerDetected));
+        }
+        assert!(runner.commands().is_empty());
+
+        dir.write("yarn.lock", "");
+        assert!(!detect::is_published_package(dir.path()));
+    }
+
+    #[test]
+    fn manager_version_runs_through_corepack() {
+        let dir = npm_project();
+        let invocation = args(&["--manager-version", "pnpm@8.15.0", "install"]);
+
+        let runner = RecordingRunner::default();
+        let (options, rest) = cli::parse_args(&invocation).unwrap();
+        run(&runner, dir.path(), options, rest).unwrap();
+        assert_eq!(
+            runner.argvs(),
+            [args(&["corepack", "pnpm@8.15.0", "install"])]
+        );
+
+        let runner = RecordingRunner {
+            missing: vec!["corepack".to_string()],
+            ..RecordingRunner::default()
+        };
+        let (options, rest) = cli::parse_args(&invocation).unwrap();
+        let result = run(&runner, dir.path(), options, rest);
+        assert_eq!(result, Err(Error::CorepackNotFound));
+    }
+
+    #[test]
+    fn overflowing_timeouts_are_invalid() {
+        for timeout in ["999999999999999999m", "999999999999999999h"] {
+            let result = cli::parse_args(&args(&["--timeout", timeout, "test"]));
+            assert_eq!(
+                result.err(),
+                Some(format!("invalid duration `{}`", timeout))
+            );
+        }
+
+        let (options, _) = cli::parse_args(&args(&["--timeout", "18446744073709551615s", "test"]))
+            .expect("fits in a Duration");
+        assert_eq!(
+            options.timeout,
+            Some(std::time::Duration::from_secs(u64::MAX))
+        );
+    }
+
+    #[test]
+    fn bare_n_without_scripts_is_a_usage_error() {
+        let dir = TempDir::new();
+        dir.write("package.json", r#"{"name":"empty","scripts":{}}"#);
+        dir.write("package-lock.json", "{}");
+        let runner = RecordingRunner::default();
+
+        let error = run(&runner, dir.path(), Options::default(), Vec::new()).unwrap_err();
+
+        assert_eq!(error, Error::NoScripts);
+        assert_eq!(error.to_string(), "no scripts defined and no command given");
+        assert_eq!(error.exit_code(), 2);
+        assert!(runner.commands().is_empty());
+    }
+
+    #[test]
+    fn piped_scripts_drive_autocorrect() {
+        let piped =
+            r#"{"name":"generated","scripts":{"typecheck":"tsc --noEmit","lint":"eslint ."}}"#;
+        let scripts = read_scripts_from(piped.as_bytes()).unwrap();
+        let config = Config::default();
+
+        let corrected = try_autocorrect_script(
+            PackageManager::Pnpm,
+            &args(&["typechck"]),
+            &scripts,
+            &config,
+        )
+        .unwrap();
+
+        assert_eq!(corrected, args(&["typecheck"]));
+        assert_eq!(
+            run_exact_script(PackageManager::Pnpm, &corrected, &scripts, &config),
+            args(&["run", "typecheck"])
+        );
+        assert!(matches!(
+            read_scripts_from("not json".as_bytes()),
+            Err(Error::Usage(_))
+        ));
+    }
+
+    // A trace sink the test can read back while `log` holds on to it
+    #[derive(Clone, Default)]
+    struct TraceBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl io::Write for TraceBuffer {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn trace_records_resolution_and_spawn_in_a_dry_run() {
+        let dir = npm_project();
+        let buffer = TraceBuffer::default();
+        log::set_trace(Box::new(buffer.clone()));
+        let options = Options {
+            print_command: true,
+            ..Options::default()
+        };
+
+        run(
+            &RecordingRunner::default(),
+            dir.path(),
+            options,
+            args(&["b"]),
+        )
+        .unwrap();
+        log::set_trace(Box::new(io::sink()));
+
+        // Other tests trace into the same sink meanwhile, only this directory's events count
+        let trace = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let root = dir.path().display().to_string();
+        let events: Vec<json::Value> = trace
+            .lines()
+            .map(|line| json::parse(line).unwrap())
+            .filter(|event| {
+                [event.get("root"), event.get("dir")]
+                    .into_iter()
+                    .flatten()
+                    .any(|dir| dir.as_str() == Some(root.as_str()))
+            })
+            .collect();
+        let event = |name: &str| {
+            events
+                .iter()
+                .find(|event| event.get("event").and_then(json::Value::as_str) == Some(name))
+                .unwrap_or_else(|| panic!("no {} event in {}", name, trace))
+        };
+
+        let resolved = event("manager_resolved");
+        assert_eq!(
+            resolved.get("manager").and_then(json::Value::as_str),
+            Some("npm")
+        );
+        assert_eq!(
+            resolved.get("source").and_then(json::Value::as_str),
+            Some("lockfile")
+        );
+
+        let spawned = event("command_spawned");
+        assert_eq!(
+            spawned.get("program").and_then(json::Value::as_str),
+            Some("npm")
+        );
+        assert_eq!(
+            spawned.get("args"),
+            Some(&json::Value::Array(vec!["run".into(), "build".into()]))
+        );
+        assert_eq!(spawned.get("dry_run"), Some(&json::Value::Bool(true)));
+    }
+
+    #[test]
+    fn init_picks_a_manager_and_only_runs_its_init() {
+        let dir = TempDir::new();
+        let runner = RecordingRunner::default();
+
+        let picked = &mut "pnpm\n".as_bytes();
+        init_project(
+            &runner,
+            picked,
+            &Options::default(),
+            &Config::default(),
+            &[],
+            dir.path(),
+        )
+        .unwrap();
+        let picked = &mut "1\n".as_bytes();
+        init_project(
+            &runner,
+            picked,
+            &Options::default(),
+            &Config::default(),
+            &[],
+            dir.path(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            runner.argvs(),
+            [args(&["pnpm", "init"]), args(&["npm", "init", "-y"])]
+        );
+        assert_eq!(runner.commands()[0].dir, dir.path());
+    }
+
+    #[test]
+    fn which_prints_the_autocorrected_script_body() {
+        let dir = TempDir::new();
+        let manifest = dir.write(
+            "package.json",
+            r#"{"scripts":{"build":"tsc -p .","test":"vitest run"}}"#,
+        );
+        let config = Config::default();
+
+        assert_eq!(
+            which_script(&manifest, &args(&["biuld"]), &config),
+            Ok("tsc -p .".to_string())
+        );
+        assert_eq!(
+            which_script(&manifest, &args(&["t"]), &config),
+            Ok("vitest run".to_string())
+        );
+
+        let missing = which_script(&manifest, &args(&["deploy"]), &config).unwrap_err();
+        assert_eq!(missing, Error::Other("no script `deploy`".to_string()));
+        assert_eq!(missing.exit_code(), 1);
+    }
+
+    #[test]
+    fn config_without_get_or_set_goes_to_the_manager() {
+        let dir = npm_project();
+        let runner = RecordingRunner::default();
+
+        run(&runner, dir.path(), Options::default(), args(&["config"])).unwrap();
+        run(
+            &runner,
+            dir.path(),
+            Options::default(),
+            args(&["config", "list"]),
+        )
+        .unwrap();
 
-    if !status.success() {
-        eprintln!("Command failed to execute");
+        assert_eq!(
+            runner.argvs(),
+            [args(&["npm", "config"]), args(&["npm", "config", "list"])]
+        );
     }
 }
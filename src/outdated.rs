@@ -0,0 +1,158 @@
+// `n outdated --json` in a workspace reports every package, keyed by package name.
+// npm and pnpm can do that themselves, for the others every package runs on its own.
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::commands::patch_commands;
+use crate::config::Config;
+use crate::error::Error;
+use crate::json::{self, Value};
+use crate::package_manager::PackageManager;
+use crate::runner::{capture_manager, ensure_allowed, run_manager, CommandRunner, CommandSpec};
+use crate::workspace::{enumerate_workspaces, read_package_name, WorkspacePackage};
+
+// `args` is the whole invocation, `outdated --json` included
+pub fn outdated_json(
+    runner: &dyn CommandRunner,
+    manager: PackageManager,
+    root: &Path,
+    args: &[String],
+    config: &Config,
+    timeout: Option<Duration>,
+) -> Result<(), Error> {
+    let native = match manager {
+        PackageManager::Npm => Some("--workspaces"),
+        PackageManager::Pnpm => Some("--recursive"),
+        _ => None,
+    };
+
+    if let Some(flag) = native {
+        // pnpm spells it `--format json`
+        let mut native_args: Vec<String> = args
+            .iter()
+            .flat_map(|arg| match (manager, arg.as_str()) {
+                (PackageManager::Pnpm, "--json") => {
+                    vec!["--format".to_string(), "json".to_string()]
+                }
+                _ => vec![arg.clone()],
+            })
+            .collect();
+        native_args.push(flag.to_string());
+
+        let patched = patch_commands(manager, &native_args, config);
+        ensure_allowed(&native_args, &patched, &config.deny)?;
+
+        let spec = CommandSpec {
+            program: config.binary(manager).to_string(),
+            args: patched,
+            dir: root.to_path_buf(),
+            timeout,
+            spinner: false,
+            env: Vec::new(),
+            prefix: None,
+            bail_on: None,
+        };
+        return run_manager(runner, manager, &spec);
+    }
+
+    let patched = patch_commands(manager, args, config);
+    ensure_allowed(args, &patched, &config.deny)?;
+
+    let root_package = WorkspacePackage {
+        name: read_package_name(root),
+        dir: root.to_path_buf(),
+    };
+
+    let mut merged = Vec::new();
+    for package in [vec![root_package], enumerate_workspaces(root)].concat() {
+        let spec = CommandSpec {
+            program: config.binary(manager).to_string(),
+            args: patched.clone(),
+            dir: package.dir.clone(),
+            timeout,
+            spinner: false,
+            env: Vec::new(),
+            prefix: None,
+            bail_on: None,
+        };
+        let (code, output) = capture_manager(runner, manager, &spec)?;
+
+        // Most managers exit with 1 when something is outdated, only no output is a failure
+        if code != 0 && output.trim().is_empty() {
+            return Err(Error::Other(format!(
+                "`{} outdated` failed in {}",
+                manager, package.name
+            )));
+        }
+
+        merged.push((package.name, parse_outdated(&output)));
+    }
+
+    println!("{}", json::to_string_pretty(&merge_outdated(merged), "  "));
+    Ok(())
+}
+
+// Nothing outdated often means no output at all. yarn 1 prints one JSON document per line.
+fn parse_outdated(output: &str) -> Value {
+    if output.trim().is_empty() {
+        return Value::Object(Vec::new());
+    }
+
+    json::parse(output).unwrap_or_else(|_| {
+        Value::Array(
+            output
+                .lines()
+                .filter_map(|line| json::parse(line).ok())
+                .collect(),
+        )
+    })
+}
+
+// Names fall back to the directory name, so two packages can share one. Later ones get
+// their position appended to stay apart.
+fn merge_outdated(results: Vec<(String, Value)>) -> Value {
+    let mut merged: Vec<(String, Value)> = Vec::new();
+
+    for (name, value) in results {
+        let key = if merged.iter().any(|(existing, _)| *existing == name) {
+            format!("{} ({})", name, merged.len())
+        } else {
+            name
+        };
+        merged.push((key, value));
+    }
+
+    Value::Object(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outputs_merge_by_package_name() {
+        let results = vec![
+            (
+                "web".to_string(),
+                parse_outdated(r#"{"react":{"current":"18.2.0","latest":"19.0.0"}}"#),
+            ),
+            ("api".to_string(), parse_outdated("\n")),
+            (
+                "ui".to_string(),
+                parse_outdated("{\"type\":\"info\"}\n{\"type\":\"table\"}\n"),
+            ),
+            ("web".to_string(), parse_outdated("{}")),
+        ];
+
+        let expected = json::parse(
+            r#"{
+                "web": {"react": {"current": "18.2.0", "latest": "19.0.0"}},
+                "api": {},
+                "ui": [{"type": "info"}, {"type": "table"}],
+                "web (3)": {}
+            }"#,
+        );
+        assert_eq!(Ok(merge_outdated(results)), expected);
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Npm,
+    Yarn,
+    Pnpm,
+    Bun,
+    Deno,
+}
+
+impl PackageManager {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Yarn => "yarn",
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Bun => "bun",
+            PackageManager::Deno => "deno",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PackageManager> {
+        match name {
+            "npm" => Some(PackageManager::Npm),
+            "yarn" => Some(PackageManager::Yarn),
+            "pnpm" => Some(PackageManager::Pnpm),
+            "bun" => Some(PackageManager::Bun),
+            "deno" => Some(PackageManager::Deno),
+            _ => None,
+        }
+    }
+
+    // The flag each manager expects for saving a package as a dev dependency
+    pub fn dev_flag(&self) -> &'static str {
+        match self {
+            PackageManager::Bun => "-d",
+            _ => "-D",
+        }
+    }
+
+    // The verb in front of a script name, yarn runs scripts by their bare name and
+    // `deno run` runs files
+    pub fn run_prefix(&self) -> Option<&'static str> {
+        match self {
+            PackageManager::Yarn => None,
+            PackageManager::Deno => Some("task"),
+            _ => Some("run"),
+        }
+    }
+
+    // npm 7+ deprecated `--production` in favour of `--omit=dev`, deno has no such flag
+    pub fn production_flag(&self) -> Option<&'static str> {
+        match self {
+            PackageManager::Npm => Some("--omit=dev"),
+            PackageManager::Yarn | PackageManager::Bun => Some("--production"),
+            PackageManager::Pnpm => Some("--prod"),
+            PackageManager::Deno => None,
+        }
+    }
+
+    // Updates the lockfile without touching node_modules, yarn's mode needs yarn 2+
+    pub fn lockfile_only_flag(&self) -> Option<&'static str> {
+        match self {
+            PackageManager::Npm => Some("--package-lock-only"),
+            PackageManager::Yarn => Some("--mode=update-lockfile"),
+            PackageManager::Pnpm | PackageManager::Bun => Some("--lockfile-only"),
+            PackageManager::Deno => None,
+        }
+    }
+
+    // Takes milliseconds. yarn 2+ only reads `httpTimeout` from .yarnrc.yml.
+    pub fn network_timeout_flag(&self) -> Option<&'static str> {
+        match self {
+            PackageManager::Npm | PackageManager::Pnpm => Some("--fetch-timeout"),
+            PackageManager::Yarn => Some("--network-timeout"),
+            PackageManager::Bun | PackageManager::Deno => None,
+        }
+    }
+
+    // npm has no flag, `npm ci` is its frozen install
+    pub fn frozen_flag(&self) -> Option<&'static str> {
+        match self {
+            PackageManager::Npm => None,
+            PackageManager::Deno => Some("--frozen"),
+            _ => Some("--frozen-lockfile"),
+        }
+    }
+
+    // deno has no way to save a peer dependency from the command line
+    pub fn peer_flag(&self) -> Option<&'static str> {
+        match self {
+            PackageManager::Deno => None,
+            PackageManager::Yarn | PackageManager::Bun => Some("--peer"),
+            PackageManager::Npm | PackageManager::Pnpm => Some("--save-peer"),
+        }
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_yarn_runs_scripts_without_a_verb() {
+        assert_eq!(PackageManager::Yarn.run_prefix(), None);
+        for manager in [
+            PackageManager::Npm,
+            PackageManager::Pnpm,
+            PackageManager::Bun,
+        ] {
+            assert_eq!(manager.run_prefix(), Some("run"), "{}", manager);
+        }
+        // deno runs package.json scripts as tasks
+        assert_eq!(PackageManager::Deno.run_prefix(), Some("task"));
+    }
+}
@@ -0,0 +1,102 @@
+// `n --pick` lists the scripts and reads which one to run from stdin, as a
+// number from the list or a script name. `n init` picks a manager the same way.
+
+use std::io::{self, BufRead, Write};
+
+use crate::error::Error;
+use crate::package_manager::PackageManager;
+
+const MANAGERS: &[PackageManager] = &[
+    PackageManager::Npm,
+    PackageManager::Yarn,
+    PackageManager::Pnpm,
+    PackageManager::Bun,
+    PackageManager::Deno,
+];
+
+pub fn pick_script(scripts: &[String]) -> Result<String, Error> {
+    if scripts.is_empty() {
+        return Err(Error::Other(
+            "there are no scripts to pick from".to_string(),
+        ));
+    }
+
+    pick_script_from(scripts, &mut io::stdin().lock())
+}
+
+pub fn pick_script_from(scripts: &[String], input: &mut dyn BufRead) -> Result<String, Error> {
+    pick(scripts, "script", input)
+}
+
+pub fn pick_manager(input: &mut dyn BufRead) -> Result<PackageManager, Error> {
+    let names: Vec<String> = MANAGERS
+        .iter()
+        .map(|manager| manager.name().to_string())
+        .collect();
+    let name = pick(&names, "package manager", input)?;
+
+    Ok(PackageManager::from_name(&name).expect("picked from the list"))
+}
+
+fn pick(items: &[String], what: &str, input: &mut dyn BufRead) -> Result<String, Error> {
+    let width = items.len().to_string().len();
+    for (index, item) in items.iter().enumerate() {
+        eprintln!("{:>width$}) {}", index + 1, item);
+    }
+    eprint!("Pick a {}: ", what);
+    let _ = io::stderr().flush();
+
+    let mut answer = String::new();
+    let read = input
+        .read_line(&mut answer)
+        .map_err(|e| Error::Other(format!("failed to read the picked {}: {}", what, e)))?;
+
+    // Nothing picked, e.g. Ctrl-D
+    if read == 0 || answer.trim().is_empty() {
+        return Err(Error::Aborted);
+    }
+
+    parse_choice(answer.trim(), items)
+        .cloned()
+        .ok_or_else(|| Error::Other(format!("`{}` is not one of the {}s", answer.trim(), what)))
+}
+
+fn parse_choice<'s>(answer: &str, items: &'s [String]) -> Option<&'s String> {
+    match answer.parse::<usize>() {
+        Ok(number) => items.get(number.checked_sub(1)?),
+        Err(_) => items.iter().find(|item| *item == answer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::patch_commands;
+    use crate::config::Config;
+    use crate::shell::argv_json;
+
+    fn scripts() -> Vec<String> {
+        ["build", "dev", "test"].map(str::to_string).to_vec()
+    }
+
+    #[test]
+    fn picked_script_prints_as_json_argv() {
+        let picked = pick(&scripts(), "script", &mut "2\n".as_bytes()).unwrap();
+
+        let args = ["run".to_string(), picked];
+        let argv = patch_commands(PackageManager::Pnpm, &args, &Config::default());
+        assert_eq!(argv_json("pnpm", &argv), r#"["pnpm","run","dev"]"#);
+    }
+
+    #[test]
+    fn picks_by_name_and_aborts_on_nothing() {
+        assert_eq!(
+            pick(&scripts(), "script", &mut "test\n".as_bytes()),
+            Ok("test".to_string())
+        );
+        assert_eq!(
+            pick(&scripts(), "script", &mut "".as_bytes()),
+            Err(Error::Aborted)
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::Mutex;
+use std::thread;
+
+// Installs mostly wait on the registry, which starts throttling long before the CPU is busy
+const INSTALL_CONCURRENCY_CAP: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Concurrency {
+    Auto,
+    Fixed(usize),
+}
+
+// Resolves `auto` to one job per CPU, capped for installs
+pub fn select_concurrency(concurrency: Option<Concurrency>, install: bool) -> usize {
+    let cpus = thread::available_parallelism().map_or(1, |cpus| cpus.get());
+    concurrency_for(concurrency, install, cpus)
+}
+
+fn concurrency_for(concurrency: Option<Concurrency>, install: bool, cpus: usize) -> usize {
+    match concurrency.unwrap_or(Concurrency::Auto) {
+        Concurrency::Fixed(count) => count,
+        Concurrency::Auto => {
+            if install {
+                cpus.min(INSTALL_CONCURRENCY_CAP)
+            } else {
+                cpus
+            }
+        }
+    }
+}
+
+// Calls `job` for every item on at most `concurrency` threads and returns the results
+// in the order of `items`
+pub fn run_bounded<T, R>(items: Vec<T>, concurrency: usize, job: impl Fn(T) -> R + Sync) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+{
+    let count = items.len();
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..count).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, count.max(1)) {
+            scope.spawn(|| loop {
+                let Some((index, item)) = queue.lock().expect("queue poisoned").next() else {
+                    break;
+                };
+
+                let result = job(item);
+                results.lock().expect("results poisoned")[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .expect("results poisoned")
+        .into_iter()
+        .map(|result| result.expect("every job ran"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_caps_installs_below_the_cpu_count() {
+        assert_eq!(concurrency_for(Some(Concurrency::Auto), true, 16), 4);
+        assert_eq!(concurrency_for(Some(Concurrency::Auto), false, 16), 16);
+        assert_eq!(concurrency_for(None, true, 2), 2);
+        assert_eq!(concurrency_for(Some(Concurrency::Fixed(8)), true, 16), 8);
+    }
+}
@@ -0,0 +1,97 @@
+// Tags every line a child writes with where it came from, so output of several runs
+// can share a terminal.
+
+use std::io::{self, Write};
+
+pub struct PrefixWriter<W: Write> {
+    inner: W,
+    prefix: String,
+    // The start of a line whose end hasn't been written yet
+    pending: Vec<u8>,
+}
+
+impl<W: Write> PrefixWriter<W> {
+    pub fn new(inner: W, name: &str) -> Self {
+        PrefixWriter {
+            inner,
+            prefix: format!("[{}] ", name),
+            pending: Vec::new(),
+        }
+    }
+
+    // Each line goes out in a single write, so lines from other writers on the same
+    // stream only ever land between whole lines
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let mut buffer = Vec::with_capacity(self.prefix.len() + line.len() + 1);
+        buffer.extend_from_slice(self.prefix.as_bytes());
+        buffer.extend_from_slice(line);
+        if !line.ends_with(b"\n") {
+            buffer.push(b'\n');
+        }
+
+        self.inner.write_all(&buffer)
+    }
+}
+
+impl<W: Write> Write for PrefixWriter<W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(bytes);
+
+        while let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            self.write_line(&line)?;
+        }
+
+        Ok(bytes.len())
+    }
+
+    // Only called once the child is done, a partial line can't be finished anymore
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.write_line(&line)?;
+        }
+
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // One terminal both writers write to
+    #[derive(Clone, Default)]
+    struct Terminal(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Terminal {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn interleaves_whole_prefixed_lines() {
+        let terminal = Terminal::default();
+        let mut web = PrefixWriter::new(terminal.clone(), "web");
+        let mut api = PrefixWriter::new(terminal.clone(), "api");
+
+        web.write_all(b"compiling").unwrap();
+        api.write_all(b"listening on 3000\nready").unwrap();
+        web.write_all(b" done\nbuilt in 2s\n").unwrap();
+        api.flush().unwrap();
+        web.flush().unwrap();
+
+        assert_eq!(
+            String::from_utf8(terminal.0.take()).unwrap(),
+            "[api] listening on 3000\n[web] compiling done\n[web] built in 2s\n[api] ready\n"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::changes::{changed_files, filter_changed, take_since};
+use crate::cli::Options;
+use crate::commands::patch_commands;
+use crate::config::Config;
+use crate::error::Error;
+use crate::package_manager::PackageManager;
+use crate::pool::{run_bounded, select_concurrency};
+use crate::runner::{ensure_allowed, run_manager, CommandRunner, CommandSpec};
+use crate::scripts::read_package_json_scripts;
+use crate::workspace::{enumerate_workspaces, read_package_name, WorkspacePackage};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunResult {
+    pub package: String,
+    pub exit_code: i32,
+    pub duration: Duration,
+}
+
+// Runs `script` in every workspace package that defines it, one per CPU at a time,
+// then prints a summary.
+// Fails when any package failed.
+pub fn run_recursive(
+    runner: &dyn CommandRunner,
+    manager: PackageManager,
+    root: &Path,
+    script: &str,
+    rest: &[String],
+    config: &Config,
+    options: &Options,
+) -> Result<(), Error> {
+    let mut packages = enumerate_workspaces(root);
+
+    // Every package runs on its own, so the root is added here rather than through
+    // npm's `--include-workspace-root` and friends
+    if options.include_root {
+        packages.insert(
+            0,
+            WorkspacePackage {
+                name: read_package_name(root),
+                dir: root.to_path_buf(),
+            },
+        );
+    }
+
+    let (since, rest) = take_since(rest);
+
+    let mut args = vec!["run".to_string(), script.to_string()];
+    args.extend(rest);
+    let patched = patch_commands(manager, &args, config);
+    ensure_allowed(&args, &patched, &config.deny)?;
+
+    packages.retain(|package| {
+        read_package_json_scripts(&package.dir.join("package.json"))
+            .iter()
+            .any(|name| name == script)
+    });
+
+    // `--since <ref>` leaves out packages without changes, which is not a failure
+    if let Some(since) = &since {
+        let defined = !packages.is_empty();
+        packages = filter_changed(packages, root, &changed_files(root, since)?);
+
+        if defined && packages.is_empty() {
+            eprintln!(
+                "n: no package defining `{}` changed since {}",
+                script, since
+            );
+            return Ok(());
+        }
+    }
+
+    // Only worth telling apart when several packages write to the terminal
+    let prefix_output = packages.len() > 1;
+    let concurrency = select_concurrency(options.concurrency, false);
+
+    let results = run_bounded(packages, concurrency, |package| {
+        eprintln!("n: running `{}` in {}", script, package.name);
+
+        let spec = CommandSpec {
+            program: config.binary(manager).to_string(),
+            args: patched.clone(),
+            dir: package.dir.clone(),
+            timeout: options.timeout,
+            spinner: false,
+            env: Vec::new(),
+            prefix: prefix_output.then(|| package.name.clone()),
+            bail_on: options
+                .bail_on_warning
+                .then(|| config.warning_marker.clone()),
+        };
+
+        let started = Instant::now();
+        let exit_code = match run_manager(runner, manager, &spec) {
+            Ok(()) => 0,
+            // Without the manager nothing else will run either
+            Err(error @ (Error::ManagerNotFound(_) | Error::ProgramNotFound(_))) => {
+                return Err(error)
+            }
+            Err(error) => error.exit_code(),
+        };
+
+        Ok(RunResult {
+            package: package.name,
+            exit_code,
+            duration: started.elapsed(),
+        })
+    });
+    let results = results.into_iter().collect::<Result<Vec<_>, Error>>()?;
+
+    if results.is_empty() {
+        return Err(Error::Other(format!(
+            "no workspace package defines `{}`",
+            script
+        )));
+    }
+
+    eprint!("\n{}", format_summary(&results));
+
+    let failed = results
+        .iter()
+        .filter(|result| result.exit_code != 0)
+        .count();
+    if failed > 0 {
+        return Err(Error::Other(format!(
+            "`{}` failed in {} of {} packages",
+            script,
+            failed,
+            results.len()
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn format_summary(results: &[RunResult]) -> String {
+    let width = results
+        .iter()
+        .map(|result| result.package.len())
+        .chain(["package".len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut summary = format!("{:<width$}  {:>4}  {:>8}\n", "package", "exit", "duration");
+
+    for result in results {
+        summary.push_str(&format!(
+            "{:<width$}  {:>4}  {:>7.1}s\n",
+            result.package,
+            result.exit_code,
+            result.duration.as_secs_f64(),
+        ));
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::RecordingRunner;
+    use crate::testing::TempDir;
+    use std::path::PathBuf;
+
+    fn result(package: &str, exit_code: i32, millis: u64) -> RunResult {
+        RunResult {
+            package: package.to_string(),
+            exit_code,
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn summary_lines_up_in_columns() {
+        let results = [result("web", 0, 1250), result("@acme/api-server", 1, 30)];
+
+        assert_eq!(
+            format_summary(&results),
+            "package           exit  duration\n\
+             web                  0      1.2s\n\
+             @acme/api-server     1      0.0s\n"
+        );
+    }
+
+    #[test]
+    fn header_is_the_minimum_width() {
+        assert_eq!(
+            format_summary(&[result("ui", 0, 0)]),
+            "package  exit  duration\nui          0      0.0s\n"
+        );
+    }
+
+    #[test]
+    fn include_root_decides_whether_the_root_runs_too() {
+        let dir = TempDir::new();
+        dir.write(
+            "package.json",
+            r#"{"workspaces":["packages/*"],"scripts":{"build":"tsc -b"}}"#,
+        );
+        dir.write("packages/a/package.json", r#"{"scripts":{"build":"tsc"}}"#);
+        dir.write("packages/b/package.json", r#"{"scripts":{"build":"tsc"}}"#);
+
+        let dirs = |include_root| {
+            let runner = RecordingRunner::default();
+            let options = Options {
+                include_root,
+                ..Options::default()
+            };
+            let config = Config::default();
+            run_recursive(
+                &runner,
+                PackageManager::Npm,
+                dir.path(),
+                "build",
+                &[],
+                &config,
+                &options,
+            )
+            .unwrap();
+
+            let mut dirs: Vec<PathBuf> = runner
+                .commands()
+                .into_iter()
+                .inspect(|spec| assert_eq!(spec.args, ["run", "build"]))
+                .map(|spec| spec.dir)
+                .collect();
+            dirs.sort();
+            dirs
+        };
+
+        let children = [dir.path().join("packages/a"), dir.path().join("packages/b")];
+        assert_eq!(dirs(false), children);
+        assert_eq!(
+            dirs(true),
+            [
+                dir.path().to_path_buf(),
+                children[0].clone(),
+                children[1].clone()
+            ]
+        );
+    }
+}
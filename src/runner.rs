@@ -0,0 +1,815 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command as ProcessCommand, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::commands::{canonical_command, expand_alias};
+use crate::error::Error;
+use crate::package_manager::PackageManager;
+use crate::prefix::PrefixWriter;
+use crate::shell;
+
+// Everything needed to spawn a command, kept separate from spawning it so the
+// process layer can be swapped out
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub dir: PathBuf,
+    pub timeout: Option<Duration>,
+    // Capture the output behind a spinner, it's only shown if the command fails
+    pub spinner: bool,
+    // Extra variables for the child on top of n's own environment
+    pub env: Vec<(String, String)>,
+    // Prefix every line of output with this name, for runs sharing the terminal
+    pub prefix: Option<String>,
+    // Fail a successful command whose output contains this marker, in any case
+    pub bail_on: Option<String>,
+}
+
+// Sync so commands can run from worker threads
+pub trait CommandRunner: Sync {
+    fn run(&self, command: &CommandSpec) -> Result<(), Error>;
+
+    // Runs a command for its stdout. stderr still goes to the terminal, the exit code is
+    // left to the caller since some commands use it to report findings.
+    fn capture(&self, command: &CommandSpec) -> Result<(i32, String), Error>;
+}
+
+// Spawns real processes
+pub struct ProcessRunner;
+
+// Where a child's stdout and stderr go
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Output {
+    Inherit,
+    // Captured behind a spinner and only shown if the command fails
+    Spinner,
+    // Line by line through n, for prefixing and scanning
+    Forward,
+}
+
+fn choose_output(spec: &CommandSpec, terminal: bool) -> Output {
+    // Without a terminal there is nobody to show a spinner to, so output streams as
+    // usual. Output that is scanned for warnings has to stay visible too.
+    if spec.spinner && spec.bail_on.is_none() && terminal {
+        Output::Spinner
+    } else if spec.prefix.is_some() || spec.bail_on.is_some() {
+        Output::Forward
+    } else {
+        Output::Inherit
+    }
+}
+
+// How one of the child's standard streams is connected
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stream {
+    Inherit,
+    Piped,
+}
+
+impl From<Stream> for Stdio {
+    fn from(stream: Stream) -> Self {
+        match stream {
+            Stream::Inherit => Stdio::inherit(),
+            Stream::Piped => Stdio::piped(),
+        }
+    }
+}
+
+// stdin, stdout and stderr for `output`. stdin always passes through, even when the
+// output is captured, so prompts and pipes into scripts keep working.
+fn stdio(output: Output) -> [Stream; 3] {
+    match output {
+        Output::Inherit => [Stream::Inherit; 3],
+        Output::Spinner | Output::Forward => [Stream::Inherit, Stream::Piped, Stream::Piped],
+    }
+}
+
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+const SPINNER_FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+impl CommandRunner for ProcessRunner {
+    fn run(&self, spec: &CommandSpec) -> Result<(), Error> {
+        let program = spec.program.as_str();
+
+        let mut command = ProcessCommand::new(program);
+        command
+            .args(&spec.args)
+            .current_dir(&spec.dir)
+            .envs(spec.env.iter().map(|(key, value)| (key, value)));
+        isolate_for_timeout(&mut command, spec.timeout);
+
+        let output = choose_output(spec, io::stderr().is_terminal());
+        let spinner = output == Output::Spinner;
+        let forward = output == Output::Forward;
+
+        let [stdin, stdout, stderr] = stdio(output);
+        command.stdin(stdin).stdout(stdout).stderr(stderr);
+
+        let mut child = command.spawn().map_err(|e| spawn_error(program, e))?;
+        let captured = spinner.then(|| capture_output(&mut child));
+        let forwarded = forward.then(|| forward_output(&mut child, spec));
+
+        // Polling is only needed to enforce a timeout or animate the spinner
+        let mut frame = 0;
+        let status = if spec.timeout.is_none() && !spinner {
+            Some(child.wait().expect("Failed to wait for command"))
+        } else {
+            wait_with_timeout(&mut child, spec.timeout, || {
+                if spinner {
+                    eprint!(
+                        "\r{} {}",
+                        SPINNER_FRAMES[frame % SPINNER_FRAMES.len()],
+                        program
+                    );
+                    let _ = io::stderr().flush();
+                    frame += 1;
+                }
+            })
+        };
+
+        if spinner {
+            eprint!("\r\x1b[2K");
+        }
+
+        let Some(status) = status else {
+            kill_process_tree(&mut child);
+            finish_forwarded(forwarded);
+            flush_output(captured);
+            return Err(Error::TimedOut {
+                program: program.to_string(),
+                after: spec.timeout.unwrap_or_default(),
+            });
+        };
+
+        let warned = finish_forwarded(forwarded);
+
+        if status.success() {
+            match &spec.bail_on {
+                Some(marker) if warned => Err(Error::Other(format!(
+                    "`{}` printed `{}`, failing because of --bail-on-warning",
+                    program, marker
+                ))),
+                _ => Ok(()),
+            }
+        } else {
+            // The spinner swallowed the output, it's needed now to see what went wrong
+            flush_output(captured);
+            Err(Error::CommandFailed {
+                program: program.to_string(),
+                // Killed by a signal, there is no exit code to pass on
+                code: status.code().unwrap_or(1),
+            })
+        }
+    }
+
+    fn capture(&self, spec: &CommandSpec) -> Result<(i32, String), Error> {
+        let program = spec.program.as_str();
+
+        let mut command = ProcessCommand::new(program);
+        command
+            .args(&spec.args)
+            .current_dir(&spec.dir)
+            .envs(spec.env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+        isolate_for_timeout(&mut command, spec.timeout);
+
+        let mut child = command.spawn().map_err(|e| spawn_error(program, e))?;
+
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let reader = thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = stdout.read_to_end(&mut buffer);
+            buffer
+        });
+
+        let Some(status) = wait_with_timeout(&mut child, spec.timeout, || {}) else {
+            kill_process_tree(&mut child);
+            return Err(Error::TimedOut {
+                program: program.to_string(),
+                after: spec.timeout.unwrap_or_default(),
+            });
+        };
+
+        let output = reader.join().unwrap_or_default();
+        Ok((
+            status.code().unwrap_or(1),
+            String::from_utf8_lossy(&output).into_owned(),
+        ))
+    }
+}
+
+// Records every command instead of spawning it. Commands fail with the queued exit codes
+// in order and succeed once they run out.
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingRunner {
+    pub commands: std::sync::Mutex<Vec<CommandSpec>>,
+    pub exit_codes: std::sync::Mutex<std::collections::VecDeque<i32>>,
+    // What `capture` hands back as stdout
+    pub output: String,
+    // Programs that aren't installed, spawning them fails like a missing binary does
+    pub missing: Vec<String>,
+}
+
+#[cfg(test)]
+impl RecordingRunner {
+    pub fn failing_with(exit_codes: &[i32]) -> Self {
+        RecordingRunner {
+            exit_codes: std::sync::Mutex::new(exit_codes.iter().copied().collect()),
+            ..RecordingRunner::default()
+        }
+    }
+
+    pub fn commands(&self) -> Vec<CommandSpec> {
+        self.commands.lock().expect("commands poisoned").clone()
+    }
+
+    // Every recorded command as a single argv, program first
+    pub fn argvs(&self) -> Vec<Vec<String>> {
+        self.commands()
+            .into_iter()
+            .map(|spec| [vec![spec.program], spec.args].concat())
+            .collect()
+    }
+
+    fn record(&self, spec: &CommandSpec) -> Result<i32, Error> {
+        self.commands
+            .lock()
+            .expect("commands poisoned")
+            .push(spec.clone());
+
+        if self.missing.contains(&spec.program) {
+            return Err(Error::ProgramNotFound(spec.program.clone()));
+        }
+        Ok(self
+            .exit_codes
+            .lock()
+            .expect("exit codes poisoned")
+            .pop_front()
+            .unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+impl CommandRunner for RecordingRunner {
+    fn run(&self, spec: &CommandSpec) -> Result<(), Error> {
+        match self.record(spec)? {
+            0 => Ok(()),
+            code => Err(Error::CommandFailed {
+                program: spec.program.clone(),
+                code,
+            }),
+        }
+    }
+
+    fn capture(&self, spec: &CommandSpec) -> Result<(i32, String), Error> {
+        Ok((self.record(spec)?, self.output.clone()))
+    }
+}
+
+type CapturedOutput = (JoinHandle<Vec<u8>>, JoinHandle<Vec<u8>>);
+
+fn capture_output(child: &mut Child) -> CapturedOutput {
+    fn drain(mut reader: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = reader.read_to_end(&mut buffer);
+            buffer
+        })
+    }
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    (drain(stdout), drain(stderr))
+}
+
+fn flush_output(captured: Option<CapturedOutput>) {
+    let Some((stdout, stderr)) = captured else {
+        return;
+    };
+
+    let _ = io::stdout().write_all(&stdout.join().unwrap_or_default());
+    let _ = io::stderr().write_all(&stderr.join().unwrap_or_default());
+}
+
+type ForwardedOutput = (JoinHandle<bool>, JoinHandle<bool>);
+
+// Copies the child's output line by line, prefixed if asked to. Each thread reports
+// whether its stream contained the `bail_on` marker.
+fn forward_output(child: &mut Child, spec: &CommandSpec) -> ForwardedOutput {
+    fn forward(
+        reader: impl Read + Send + 'static,
+        mut writer: Box<dyn Write + Send>,
+        marker: Option<String>,
+    ) -> JoinHandle<bool> {
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut line = Vec::new();
+            let mut found = false;
+
+            while reader
+                .read_until(b'\n', &mut line)
+                .is_ok_and(|read| read > 0)
+            {
+                found = found
+                    || marker
+                        .as_deref()
+                        .is_some_and(|marker| contains(&line, marker));
+                let _ = writer.write_all(&line);
+                line.clear();
+            }
+
+            let _ = writer.flush();
+            found
+        })
+    }
+
+    fn writer(
+        stream: impl Write + Send + 'static,
+        prefix: &Option<String>,
+    ) -> Box<dyn Write + Send> {
+        match prefix {
+            Some(prefix) => Box::new(PrefixWriter::new(stream, prefix)),
+            None => Box::new(stream),
+        }
+    }
+
+    let marker = spec.bail_on.as_ref().map(|marker| marker.to_lowercase());
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    (
+        forward(stdout, writer(io::stdout(), &spec.prefix), marker.clone()),
+        forward(stderr, writer(io::stderr(), &spec.prefix), marker),
+    )
+}
+
+// `marker` is lowercase already
+fn contains(line: &[u8], marker: &str) -> bool {
+    String::from_utf8_lossy(line)
+        .to_lowercase()
+        .contains(marker)
+}
+
+// Waits until everything the child wrote made it out, true if the marker showed up
+fn finish_forwarded(forwarded: Option<ForwardedOutput>) -> bool {
+    let Some((stdout, stderr)) = forwarded else {
+        return false;
+    };
+
+    let stdout = stdout.join().unwrap_or(false);
+    let stderr = stderr.join().unwrap_or(false);
+    stdout || stderr
+}
+
+// Runs a fully specified manager command, `spec.program` being the manager, its shim or
+// corepack
+pub fn run_manager(
+    runner: &dyn CommandRunner,
+    manager: PackageManager,
+    spec: &CommandSpec,
+) -> Result<(), Error> {
+    runner
+        .run(spec)
+        .map_err(|error| manager_error(manager, error))
+}
+
+// `run_manager` for a command whose stdout is needed
+pub fn capture_manager(
+    runner: &dyn CommandRunner,
+    manager: PackageManager,
+    spec: &CommandSpec,
+) -> Result<(i32, String), Error> {
+    runner
+        .capture(spec)
+        .map_err(|error| manager_error(manager, error))
+}
+
+fn manager_error(manager: PackageManager, error: Error) -> Error {
+    match error {
+        Error::ProgramNotFound(program) if program == manager.name() => {
+            Error::ManagerNotFound(manager)
+        }
+        Error::ProgramNotFound(program) if program == "corepack" => Error::CorepackNotFound,
+        error => error,
+    }
+}
+
+pub fn run_program(
+    runner: &dyn CommandRunner,
+    program: &str,
+    args: &[String],
+    dir: &Path,
+    timeout: Option<Duration>,
+) -> Result<(), Error> {
+    runner.run(&CommandSpec {
+        program: program.to_string(),
+        args: args.to_vec(),
+        dir: dir.to_path_buf(),
+        timeout,
+        spinner: false,
+        env: Vec::new(),
+        prefix: None,
+        bail_on: None,
+    })
+}
+
+fn spawn_error(program: &str, error: io::Error) -> Error {
+    match error.kind() {
+        ErrorKind::NotFound => Error::ProgramNotFound(program.to_string()),
+        _ => Error::Other(format!("failed to run {}: {}", program, error)),
+    }
+}
+
+// Refuses commands listed under `deny`, checked on the command as typed and as patched,
+// so neither an alias nor a manager translation like bun's `exec` to `x` gets around it
+pub fn ensure_allowed(args: &[String], patched: &[String], deny: &[String]) -> Result<(), Error> {
+    let typed = args.first().map(|command| expand_alias(command));
+    let ran = patched.first().map(String::as_str);
+
+    match [typed, ran]
+        .into_iter()
+        .flatten()
+        .find(|command| is_listed(command, deny))
+    {
+        Some(command) => Err(Error::Denied(command.to_string())),
+        None => Ok(()),
+    }
+}
+
+fn is_listed(command: &str, list: &[String]) -> bool {
+    let command = canonical_command(command);
+    list.iter()
+        .any(|listed| canonical_command(listed) == command)
+}
+
+// Asks before running commands listed under `confirm`, matched on the patched command.
+// The prompt shows what actually runs. Without a terminal to ask on the command just runs.
+pub fn confirm_command(
+    patched: &[String],
+    program: &str,
+    argv: &[String],
+    confirm: &[String],
+) -> bool {
+    confirm_command_with(patched, program, argv, confirm, |prompt| ask(prompt, false))
+}
+
+fn confirm_command_with(
+    patched: &[String],
+    program: &str,
+    argv: &[String],
+    confirm: &[String],
+    ask: impl FnOnce(&str) -> Option<bool>,
+) -> bool {
+    let needs_confirmation = patched
+        .first()
+        .is_some_and(|command| is_listed(command, confirm));
+
+    if !needs_confirmation {
+        return true;
+    }
+
+    let prompt = format!("Run `{}`?", shell::command_line(program, argv));
+    ask(&prompt).unwrap_or(true)
+}
+
+// A yes/no question on stderr. `yes` answers it up front, None means there was no
+// terminal to ask on.
+pub fn ask(prompt: &str, yes: bool) -> Option<bool> {
+    if yes {
+        return Some(true);
+    }
+
+    if !io::stdin().is_terminal() {
+        return None;
+    }
+
+    Some(ask_from(prompt, &mut io::stdin().lock()))
+}
+
+fn ask_from(prompt: &str, input: &mut dyn BufRead) -> bool {
+    eprint!("{} [y/N] ", prompt);
+    let _ = io::stderr().flush();
+
+    let mut answer = String::new();
+    if input.read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+// Waits for the child, calling `on_tick` while it runs. Returns None once `timeout` passes,
+// a timeout too far out to represent never passes.
+fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Option<Duration>,
+    mut on_tick: impl FnMut(),
+) -> Option<ExitStatus> {
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    let mut last_tick: Option<Instant> = None;
+
+    #[cfg(unix)]
+    let _interrupts = timeout.map(|_| group::ForwardInterrupts::catch());
+
+    loop {
+        if let Some(status) = child.try_wait().expect("Failed to wait for command") {
+            return Some(status);
+        }
+
+        #[cfg(unix)]
+        if timeout.is_some() {
+            group::forward_interrupt(child.id());
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return None;
+        }
+
+        if last_tick.is_none_or(|tick| tick.elapsed() >= TICK_INTERVAL) {
+            on_tick();
+            last_tick = Some(Instant::now());
+        }
+
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+// A command with a timeout gets its own process group, so everything it started can be
+// killed with it. That takes it off the terminal's foreground group, which is why n
+// passes Ctrl-C on while waiting.
+fn isolate_for_timeout(command: &mut ProcessCommand, timeout: Option<Duration>) {
+    #[cfg(unix)]
+    if timeout.is_some() {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    #[cfg(not(unix))]
+    let _ = (command, timeout);
+}
+
+// Only called for commands started by `isolate_for_timeout`, whose pid is their group id
+fn kill_process_tree(child: &mut Child) {
+    #[cfg(unix)]
+    group::kill_group(child.id());
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(unix)]
+mod group {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    const SIGINT: i32 = 2;
+    const SIGKILL: i32 = 9;
+
+    static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" {
+        fn kill(pid: i32, signal: i32) -> i32;
+        fn signal(signal: i32, handler: usize) -> usize;
+    }
+
+    extern "C" fn on_interrupt(_: i32) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+
+    // Catches Ctrl-C until dropped, then puts the previous handler back
+    pub struct ForwardInterrupts {
+        previous: usize,
+    }
+
+    impl ForwardInterrupts {
+        pub fn catch() -> Self {
+            INTERRUPTED.store(false, Ordering::SeqCst);
+            let handler = on_interrupt as extern "C" fn(i32) as usize;
+            // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+            let previous = unsafe { signal(SIGINT, handler) };
+            ForwardInterrupts { previous }
+        }
+    }
+
+    impl Drop for ForwardInterrupts {
+        fn drop(&mut self) {
+            // SAFETY: restores whatever handler was installed before `catch`
+            unsafe { signal(SIGINT, self.previous) };
+        }
+    }
+
+    pub fn forward_interrupt(pid: u32) {
+        if INTERRUPTED.swap(false, Ordering::SeqCst) {
+            signal_group(pid, SIGINT);
+        }
+    }
+
+    pub fn kill_group(pid: u32) {
+        signal_group(pid, SIGKILL);
+    }
+
+    fn signal_group(pid: u32, signal: i32) {
+        let Ok(pid) = i32::try_from(pid) else {
+            return;
+        };
+        // SAFETY: a negative pid signals the child's own process group and nothing else
+        unsafe { kill(-pid, signal) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::TIMEOUT_EXIT_CODE;
+
+    fn spec(program: &str, args: &[&str]) -> CommandSpec {
+        CommandSpec {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            dir: std::env::temp_dir(),
+            timeout: None,
+            spinner: false,
+            env: Vec::new(),
+            prefix: None,
+            bail_on: None,
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_kills_the_child() {
+        let started = Instant::now();
+        let command = CommandSpec {
+            timeout: Some(Duration::from_millis(100)),
+            ..spec("sleep", &["10"])
+        };
+
+        let error = ProcessRunner.run(&command).unwrap_err();
+
+        assert_eq!(error.exit_code(), TIMEOUT_EXIT_CODE);
+        assert!(matches!(error, Error::TimedOut { .. }));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_kills_what_the_child_started() {
+        let dir = crate::testing::TempDir::new();
+        let pid_file = dir.path().join("pid");
+        let command = CommandSpec {
+            timeout: Some(Duration::from_millis(200)),
+            ..spec(
+                "sh",
+                &[
+                    "-c",
+                    &format!("sleep 30 & echo $! > {}; wait", pid_file.display()),
+                ],
+            )
+        };
+
+        let error = ProcessRunner.run(&command).unwrap_err();
+        assert!(matches!(error, Error::TimedOut { .. }));
+
+        // Orphans are reaped by init, until then a killed one shows up as a zombie
+        let pid = std::fs::read_to_string(&pid_file).expect("sleep started");
+        let state = ProcessCommand::new("ps")
+            .args(["-o", "stat=", "-p", pid.trim()])
+            .output()
+            .expect("ps runs");
+        let state = String::from_utf8_lossy(&state.stdout);
+        assert!(
+            state.trim().is_empty() || state.trim().starts_with('Z'),
+            "sleep {} outlived the timeout",
+            pid.trim()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_past_the_end_of_time_never_passes() {
+        let command = CommandSpec {
+            timeout: Some(Duration::MAX),
+            ..spec("true", &[])
+        };
+
+        assert_eq!(ProcessRunner.run(&command), Ok(()));
+        assert_eq!(ProcessRunner.capture(&command), Ok((0, String::new())));
+    }
+
+    fn strings(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn listed_command_prompts_with_the_real_program() {
+        let mut prompts = Vec::new();
+
+        let confirmed = confirm_command_with(
+            &strings("remove lodash"),
+            "corp-pnpm",
+            &strings("remove lodash"),
+            &strings("rm"),
+            |prompt| {
+                prompts.push(prompt.to_string());
+                Some(false)
+            },
+        );
+
+        assert!(!confirmed);
+        assert_eq!(prompts, ["Run `corp-pnpm remove lodash`?"]);
+        assert_ne!(Error::Aborted.exit_code(), 0);
+    }
+
+    #[test]
+    fn unlisted_command_runs_without_asking() {
+        let confirmed = confirm_command_with(
+            &strings("install"),
+            "npm",
+            &strings("install"),
+            &strings("remove"),
+            |_| panic!("asked for an unlisted command"),
+        );
+
+        assert!(confirmed);
+    }
+
+    #[test]
+    fn only_yes_confirms() {
+        for (answer, confirmed) in [
+            ("y\n", true),
+            ("YES\n", true),
+            ("\n", false),
+            ("no\n", false),
+        ] {
+            assert_eq!(ask_from("Run?", &mut answer.as_bytes()), confirmed);
+        }
+    }
+
+    #[test]
+    fn spinner_only_with_a_terminal() {
+        let install = CommandSpec {
+            spinner: true,
+            ..spec("npm", &["install"])
+        };
+
+        assert_eq!(choose_output(&install, true), Output::Spinner);
+        assert_eq!(choose_output(&install, false), Output::Inherit);
+    }
+
+    #[test]
+    fn scanned_output_stays_visible() {
+        let install = CommandSpec {
+            spinner: true,
+            bail_on: Some("warning:".to_string()),
+            ..spec("npm", &["install"])
+        };
+
+        assert_eq!(choose_output(&install, true), Output::Forward);
+    }
+
+    #[test]
+    fn stdin_passes_through_in_every_mode() {
+        let prefixed = CommandSpec {
+            prefix: Some("web".to_string()),
+            ..spec("npm", &["run", "build"])
+        };
+        let install = CommandSpec {
+            spinner: true,
+            ..spec("npm", &["install"])
+        };
+        let piped = [Stream::Inherit, Stream::Piped, Stream::Piped];
+
+        assert_eq!(
+            stdio(choose_output(&spec("npm", &["test"]), true)),
+            [Stream::Inherit; 3]
+        );
+        assert_eq!(stdio(choose_output(&install, true)), piped);
+        assert_eq!(stdio(choose_output(&prefixed, true)), piped);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn warning_marker_fails_a_successful_command() {
+        let script = |script: &str| CommandSpec {
+            bail_on: Some("warning:".to_string()),
+            ..spec("sh", &["-c", script])
+        };
+
+        let warned = ProcessRunner.run(&script("echo 'WARNING: peer dependency missing'"));
+        assert!(matches!(warned, Err(Error::Other(_))), "{:?}", warned);
+        assert_ne!(warned.unwrap_err().exit_code(), 0);
+
+        assert_eq!(ProcessRunner.run(&script("echo built")), Ok(()));
+        assert_eq!(
+            ProcessRunner.run(&script("echo built; exit 3")),
+            Err(Error::CommandFailed {
+                program: "sh".to_string(),
+                code: 3
+            })
+        );
+    }
+}
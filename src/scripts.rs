@@ -0,0 +1,452 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::json::{self, Value};
+use crate::log;
+
+// Shorter inputs are too ambiguous to be treated as an abbreviation
+const MIN_PREFIX_LENGTH: usize = 2;
+
+// The closest package.json at or above `dir`, `dir/package.json` if there is none
+pub fn find_nearest_manifest(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .map(|ancestor| ancestor.join("package.json"))
+        .find(|manifest| manifest.is_file())
+        .unwrap_or_else(|| dir.join("package.json"))
+}
+
+pub fn read_package_json_scripts(manifest: &Path) -> Vec<String> {
+    read_package_json_scripts_full(manifest)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect()
+}
+
+// Script names together with the command each one runs
+pub fn read_package_json_scripts_full(manifest: &Path) -> Vec<(String, String)> {
+    let Ok(contents) = fs::read_to_string(manifest) else {
+        return Vec::new();
+    };
+
+    let Ok(package_json) = json::parse(&contents) else {
+        log::verbose(format_args!("{} is not valid JSON", manifest.display()));
+        return Vec::new();
+    };
+
+    match scripts_object(&package_json) {
+        Ok(scripts) => scripts
+            .iter()
+            .map(|(name, command)| {
+                let command = command.as_str().unwrap_or_default().to_string();
+                (name.clone(), command)
+            })
+            .collect(),
+        Err(problem) => {
+            log::verbose(format_args!(
+                "{} in {}, ignoring it",
+                problem,
+                manifest.display()
+            ));
+            Vec::new()
+        }
+    }
+}
+
+// The `scripts` entries, or what is wrong with them. A missing `scripts` is fine.
+fn scripts_object(package_json: &Value) -> Result<&[(String, Value)], String> {
+    match package_json.get("scripts") {
+        None => Ok(&[]),
+        Some(scripts) => scripts
+            .as_object()
+            .ok_or_else(|| "`scripts` is not an object".to_string()),
+    }
+}
+
+// Script names from a package.json passed in directly. It was handed over on purpose,
+// so anything but a valid manifest is an error.
+pub fn parse_package_json_scripts(contents: &str) -> Result<Vec<String>, String> {
+    let package_json =
+        json::parse(contents).map_err(|e| format!("the piped package.json is invalid: {}", e))?;
+
+    match package_json.get("scripts") {
+        None => Ok(Vec::new()),
+        Some(scripts) => scripts
+            .as_object()
+            .map(|scripts| scripts.iter().map(|(name, _)| name.clone()).collect())
+            .ok_or_else(|| "`scripts` in the piped package.json is not an object".to_string()),
+    }
+}
+
+// The script a bare `n` runs, from `"n": { "default": "dev" }` in package.json
+pub fn read_script_default(manifest: &Path) -> Option<String> {
+    let contents = fs::read_to_string(manifest).ok()?;
+    let package_json = json::parse(&contents).ok()?;
+
+    package_json
+        .get("n")?
+        .get("default")?
+        .as_str()
+        .map(str::to_string)
+}
+
+// Writes `n.default` into package.json, keeping its key order and indentation
+pub fn write_script_default(manifest: &Path, script: &str) -> Result<(), String> {
+    let contents = fs::read_to_string(manifest)
+        .map_err(|e| format!("failed to read {}: {}", manifest.display(), e))?;
+    let package_json =
+        json::parse(&contents).map_err(|e| format!("{}: {}", manifest.display(), e))?;
+
+    if !read_package_json_scripts(manifest)
+        .iter()
+        .any(|name| name == script)
+    {
+        return Err(format!("no script `{}` in {}", script, manifest.display()));
+    }
+
+    let Value::Object(mut entries) = package_json else {
+        return Err(format!("{} is not a JSON object", manifest.display()));
+    };
+
+    let default = ("default".to_string(), Value::String(script.to_string()));
+    match entries.iter_mut().find(|(key, _)| key == "n") {
+        Some((_, Value::Object(settings))) => {
+            match settings.iter_mut().find(|(key, _)| key == "default") {
+                Some(entry) => *entry = default,
+                None => settings.push(default),
+            }
+        }
+        Some((_, value)) => *value = Value::Object(vec![default]),
+        None => entries.push(("n".to_string(), Value::Object(vec![default]))),
+    }
+
+    // Keep whatever indentation the file already uses, npm's two spaces otherwise
+    let indent: String = contents
+        .lines()
+        .nth(1)
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).collect())
+        .filter(|indent: &String| !indent.is_empty())
+        .unwrap_or_else(|| "  ".to_string());
+
+    let mut updated = json::to_string_pretty(&Value::Object(entries), &indent);
+    if contents.ends_with('\n') {
+        updated.push('\n');
+    }
+
+    fs::write(manifest, updated)
+        .map_err(|e| format!("failed to write {}: {}", manifest.display(), e))
+}
+
+// Groups `test:unit`, `test:e2e` and friends under `test`, one level of indentation
+// per `:` segment
+pub fn format_script_tree(scripts: &[String]) -> String {
+    #[derive(Default)]
+    struct Group {
+        children: BTreeMap<String, Group>,
+    }
+
+    fn render(group: &Group, depth: usize, output: &mut String) {
+        for (name, child) in &group.children {
+            output.push_str(&"  ".repeat(depth));
+            output.push_str(name);
+            output.push('\n');
+            render(child, depth + 1, output);
+        }
+    }
+
+    let mut root = Group::default();
+    for script in scripts {
+        let mut group = &mut root;
+        for segment in script.split(':') {
+            group = group.children.entry(segment.to_string()).or_default();
+        }
+    }
+
+    let mut output = String::new();
+    render(&root, 0, &mut output);
+    output
+}
+
+// Finds the candidate closest to `input`, ignoring case and separators.
+// Returns None when nothing is close enough to be a plausible typo.
+pub fn find_similar_command(input: &str, candidates: &[String]) -> Option<String> {
+    let similar = find_similar_commands(input, candidates);
+
+    // `type-check` and `typecheck` can't be told apart by a typo, so don't guess
+    if has_normalized_twins(&similar) {
+        log::verbose(format_args!(
+            "`{}` is ambiguous between scripts that only differ in case or separators",
+            input
+        ));
+        return None;
+    }
+
+    similar.into_iter().next()
+}
+
+// Whether some of `names` only differ in case or separators
+pub fn has_normalized_twins(names: &[String]) -> bool {
+    names.iter().enumerate().any(|(index, name)| {
+        names[index + 1..]
+            .iter()
+            .any(|other| normalize(other) == normalize(name))
+    })
+}
+
+// Every candidate tied for closest to `input`, in the order given. Empty when nothing
+// is close enough.
+pub fn find_similar_commands(input: &str, candidates: &[String]) -> Vec<String> {
+    if let Some(expanded) = expand_abbreviation(input, candidates) {
+        return vec![expanded];
+    }
+
+    let normalized_input = normalize(input);
+    let max_distance = (normalized_input.chars().count() / 3).max(1);
+
+    let mut best: Option<((usize, usize), Vec<&String>)> = None;
+
+    for candidate in candidates {
+        let normalized_candidate = normalize(candidate);
+
+        // Abbreviations beat typos, and the shortest command an abbreviation
+        // expands to is almost always the one that was meant
+        let score = if normalized_input.chars().count() >= MIN_PREFIX_LENGTH
+            && normalized_candidate.starts_with(&normalized_input)
+        {
+            (0, normalized_candidate.chars().count())
+        } else {
+            let distance = levenshtein(&normalized_input, &normalized_candidate);
+            if distance > max_distance {
+                continue;
+            }
+            (1, distance)
+        };
+
+        match &mut best {
+            Some((best_score, tied)) if score == *best_score => tied.push(candidate),
+            Some((best_score, _)) if score > *best_score => {}
+            _ => best = Some((score, vec![candidate])),
+        }
+    }
+
+    let Some((_, tied)) = best else {
+        return Vec::new();
+    };
+
+    tied.into_iter().cloned().collect()
+}
+
+// Treats `input` as the initials of a script's words, so `tc` finds `typeCheck`,
+// `type-check` or `type:check`. Ambiguous abbreviations expand to nothing.
+fn expand_abbreviation(input: &str, candidates: &[String]) -> Option<String> {
+    if input.chars().count() < MIN_PREFIX_LENGTH {
+        return None;
+    }
+
+    let input = input.to_ascii_lowercase();
+    let mut matches = candidates
+        .iter()
+        .filter(|candidate| word_initials(candidate) == input);
+
+    match (matches.next(), matches.next()) {
+        (Some(candidate), None) => Some(candidate.clone()),
+        _ => None,
+    }
+}
+
+fn word_initials(name: &str) -> String {
+    let mut initials = String::new();
+    let mut previous: Option<char> = None;
+
+    for c in name.chars() {
+        let starts_word = match previous {
+            None => true,
+            Some(previous) => {
+                matches!(previous, '-' | '_' | ':')
+                    || (previous.is_ascii_lowercase() && c.is_ascii_uppercase())
+            }
+        };
+
+        if starts_word && !matches!(c, '-' | '_' | ':') {
+            initials.push(c.to_ascii_lowercase());
+        }
+        previous = Some(c);
+    }
+
+    initials
+}
+
+// Script names are ASCII in practice, so only ASCII letters are folded. Full Unicode
+// lowercasing would make matching depend on special cases like the Turkish dotted I.
+fn normalize(name: &str) -> String {
+    name.to_ascii_lowercase()
+        .chars()
+        .filter(|c| !matches!(c, '-' | '_' | ':'))
+        .collect()
+}
+
+// Edit distance where swapping two adjacent characters counts as a single edit
+fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+
+            let mut distance = (distances[i - 1][j - 1] + cost)
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn scripts(names: &str) -> Vec<String> {
+        names.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn similar(input: &str, names: &str) -> Option<String> {
+        find_similar_command(input, &scripts(names))
+    }
+
+    #[test]
+    fn shortest_prefix_match_wins() {
+        assert_eq!(similar("bu", "bundle build"), Some("build".to_string()));
+        assert_eq!(similar("bu", "builder bundle"), Some("bundle".to_string()));
+    }
+
+    #[test]
+    fn prefix_matches_beat_typos() {
+        // `dev` is a single edit away, but `dep` starts `deploy`
+        assert_eq!(similar("dep", "dev deploy"), Some("deploy".to_string()));
+        assert_eq!(similar("biuld", "build bundle"), Some("build".to_string()));
+    }
+
+    #[test]
+    fn single_characters_are_not_prefixes() {
+        assert_eq!(similar("x", "build lint"), None);
+    }
+
+    #[test]
+    fn initials_expand_to_the_script() {
+        for name in ["type-check", "typeCheck", "type:check"] {
+            assert_eq!(
+                similar("tc", &format!("build {} test", name)),
+                Some(name.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn ambiguous_initials_expand_to_nothing() {
+        assert_eq!(
+            find_similar_commands("tc", &scripts("type-check test:coverage")),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn capital_i_folds_to_ascii_i() {
+        assert_eq!(normalize("buildIOS"), "buildios");
+        assert_eq!(normalize("İnit"), "İnit");
+        assert_eq!(
+            similar("buildios", "buildIOS"),
+            Some("buildIOS".to_string())
+        );
+        assert_eq!(
+            similar("BUILDIOS", "buildIOS"),
+            Some("buildIOS".to_string())
+        );
+    }
+
+    #[test]
+    fn scripts_that_are_not_an_object_are_ignored() {
+        for scripts in [r#""build""#, r#"["build"]"#, "null"] {
+            let package_json = json::parse(&format!(r#"{{"scripts":{}}}"#, scripts)).unwrap();
+
+            assert_eq!(
+                scripts_object(&package_json),
+                Err("`scripts` is not an object".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn manifest_with_broken_scripts_has_none() {
+        let dir = TempDir::new();
+        let manifest = dir.write("package.json", r#"{"scripts":["build"]}"#);
+
+        assert_eq!(read_package_json_scripts(&manifest), Vec::<String>::new());
+    }
+
+    #[test]
+    fn normalized_twins_are_a_tie() {
+        let names = "type-check typecheck lint";
+
+        assert_eq!(similar("typechek", names), None);
+        assert_eq!(
+            find_similar_commands("typechek", &scripts(names)),
+            scripts("type-check typecheck")
+        );
+        assert_eq!(similar("lnt", names), Some("lint".to_string()));
+    }
+
+    #[test]
+    fn set_script_default_writes_the_field() {
+        let dir = TempDir::new();
+        let manifest = dir.write(
+            "package.json",
+            "{\n    \"name\": \"app\",\n    \"scripts\": {\n        \"dev\": \"vite\"\n    }\n}\n",
+        );
+
+        write_script_default(&manifest, "dev").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&manifest).unwrap(),
+            "{\n    \"name\": \"app\",\n    \"scripts\": {\n        \"dev\": \"vite\"\n    },\n    \
+             \"n\": {\n        \"default\": \"dev\"\n    }\n}\n"
+        );
+        assert_eq!(read_script_default(&manifest), Some("dev".to_string()));
+    }
+
+    #[test]
+    fn set_script_default_rejects_an_unknown_script() {
+        let dir = TempDir::new();
+        let contents = r#"{"scripts":{"dev":"vite"}}"#;
+        let manifest = dir.write("package.json", contents);
+
+        let error = write_script_default(&manifest, "start").unwrap_err();
+
+        assert!(error.starts_with("no script `start`"), "{}", error);
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), contents);
+    }
+
+    #[test]
+    fn script_tree_groups_by_namespace() {
+        let names = "test:unit build lint test:e2e:ci test build:types test:e2e";
+
+        assert_eq!(
+            format_script_tree(&scripts(names)),
+            "build\n  types\nlint\ntest\n  e2e\n    ci\n  unit\n"
+        );
+    }
+}
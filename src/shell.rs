@@ -0,0 +1,130 @@
+use crate::json;
+
+// Quotes an argument for a POSIX shell, leaving it untouched when that's safe
+pub fn quote(arg: &str) -> String {
+    let is_safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c));
+
+    if is_safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+// Renders a program and its arguments as a single line that can be pasted into a shell
+pub fn command_line(program: &str, args: &[String]) -> String {
+    let mut line = quote(program);
+    for arg in args {
+        line.push(' ');
+        line.push_str(&quote(arg));
+    }
+    line
+}
+
+// `["pnpm","run","build"]`, for wrappers that want to run the command themselves
+pub fn argv_json(program: &str, args: &[String]) -> String {
+    let argv = [program]
+        .into_iter()
+        .chain(args.iter().map(String::as_str))
+        .map(|arg| json::Value::String(arg.to_string()))
+        .collect();
+
+    json::to_string(&json::Value::Array(argv))
+}
+
+// Splits a line into words like a POSIX shell would, minus expansions: whitespace
+// separates words, quotes group them and a backslash escapes the next character
+pub fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(format!("unterminated quote in `{}`", line)),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(format!("unterminated quote in `{}`", line)),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(format!("unterminated quote in `{}`", line)),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                if let Some(c) = chars.next() {
+                    word.push(c);
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        words.push(word);
+    }
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn arguments_with_spaces_are_quoted() {
+        assert_eq!(
+            command_line(
+                "npm",
+                &strings(&["run", "test", "--", "--grep", "two words"])
+            ),
+            "npm run test -- --grep 'two words'"
+        );
+        assert_eq!(
+            command_line("yarn", &strings(&["it's", "", "a@1.0.0"])),
+            "yarn 'it'\\''s' '' a@1.0.0"
+        );
+    }
+
+    #[test]
+    fn quoted_line_splits_back_into_the_arguments() {
+        let args = strings(&["say", "two words", "it's", "$HOME"]);
+        assert_eq!(split(&command_line("n", &args)).unwrap()[1..], args);
+    }
+}
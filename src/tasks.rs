@@ -0,0 +1,208 @@
+use std::fs;
+use std::path::Path;
+
+const JUSTFILES: &[&str] = &["justfile", "Justfile", ".justfile"];
+const TASKFILES: &[&str] = &[
+    "Taskfile.yml",
+    "Taskfile.yaml",
+    "taskfile.yml",
+    "taskfile.yaml",
+];
+
+// Keywords that start a justfile line without being a recipe
+const JUST_KEYWORDS: &[&str] = &["alias", "set", "export", "import", "mod"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskRunner {
+    Just,
+    Task,
+}
+
+impl TaskRunner {
+    pub fn program(&self) -> &'static str {
+        match self {
+            TaskRunner::Just => "just",
+            TaskRunner::Task => "task",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub name: String,
+    pub runner: TaskRunner,
+}
+
+// Collects recipes from a justfile and tasks from a Taskfile next to package.json
+pub fn read_tasks(dir: &Path) -> Vec<Task> {
+    let mut tasks = Vec::new();
+
+    if let Some(contents) = read_first(dir, JUSTFILES) {
+        tasks.extend(
+            parse_justfile_recipes(&contents)
+                .into_iter()
+                .map(|name| Task {
+                    name,
+                    runner: TaskRunner::Just,
+                }),
+        );
+    }
+
+    if let Some(contents) = read_first(dir, TASKFILES) {
+        tasks.extend(
+            parse_taskfile_tasks(&contents)
+                .into_iter()
+                .map(|name| Task {
+                    name,
+                    runner: TaskRunner::Task,
+                }),
+        );
+    }
+
+    tasks
+}
+
+pub fn parse_justfile_recipes(contents: &str) -> Vec<String> {
+    let mut recipes = Vec::new();
+    let mut private = false;
+
+    for line in contents.lines() {
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+
+        if line.trim().is_empty() || line.starts_with(|c: char| c.is_whitespace() || c == '#') {
+            continue;
+        }
+
+        // Any other top level line ends the attributes of the previous one
+        let is_private = std::mem::take(&mut private);
+
+        let Some((header, after)) = line.split_once(':') else {
+            continue;
+        };
+
+        // `name := value` is an assignment
+        if after.starts_with('=') {
+            continue;
+        }
+
+        let Some(name) = header.split_whitespace().next() else {
+            continue;
+        };
+        let name = name.trim_start_matches('@');
+
+        let is_recipe = !name.is_empty()
+            && !is_private
+            && !name.starts_with('_')
+            && !JUST_KEYWORDS.contains(&name)
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        if is_recipe {
+            recipes.push(name.to_string());
+        }
+    }
+
+    recipes
+}
+
+// Reads the keys directly under the top-level `tasks:` mapping
+pub fn parse_taskfile_tasks(contents: &str) -> Vec<String> {
+    let mut tasks = Vec::new();
+    let mut in_tasks = false;
+    let mut task_indent: Option<usize> = None;
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start().len();
+
+        if indent == 0 {
+            in_tasks = trimmed == "tasks:";
+            continue;
+        }
+
+        if !in_tasks {
+            continue;
+        }
+
+        let indent = *task_indent.get_or_insert(indent);
+        if line.len() - line.trim_start().len() != indent {
+            continue;
+        }
+
+        // Quoted names like `'lint:fix':` may contain the colon themselves
+        let name = match trimmed.chars().next() {
+            Some(quote @ ('"' | '\'')) => trimmed[1..].split_once(quote).map(|(name, _)| name),
+            _ => trimmed.split_once(':').map(|(name, _)| name.trim()),
+        };
+        if let Some(name) = name {
+            tasks.push(name.to_string());
+        }
+    }
+
+    tasks
+}
+
+fn read_first(dir: &Path, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| fs::read_to_string(dir.join(name)).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JUSTFILE: &str = r#"
+set shell := ["bash", "-c"]
+version := "1.0.0"
+alias b := build
+
+# Compiles everything
+build target="debug": deps
+    cargo build --{{target}}
+
+@test *args:
+    cargo test {{args}}
+
+[private]
+deps:
+    npm ci
+
+_helper:
+    echo hidden
+
+release-notes:
+    echo "notes: {{version}}"
+"#;
+
+    #[test]
+    fn justfile_recipes_are_extracted() {
+        assert_eq!(
+            parse_justfile_recipes(JUSTFILE),
+            ["build", "test", "release-notes"]
+        );
+    }
+
+    #[test]
+    fn taskfile_tasks_are_extracted() {
+        let taskfile = r#"
+version: '3'
+tasks:
+  build:
+    cmds:
+      - go build
+  'lint:fix':
+    cmds: [golangci-lint run --fix]
+"#;
+
+        assert_eq!(parse_taskfile_tasks(taskfile), ["build", "lint:fix"]);
+    }
+}
@@ -0,0 +1,81 @@
+// Helpers shared by the unit tests
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
+use std::time::{Duration, SystemTime};
+
+use crate::commands::CI_VARIABLES;
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+static ISOLATE_ENV: Once = Once::new();
+
+// Keeps the developer's own config, the manager that launched `cargo test` and the CI
+// the tests run on out of detection, config loading and command patching
+pub fn isolate_env() {
+    ISOLATE_ENV.call_once(|| {
+        let missing = env::temp_dir().join(format!("n-test-{}-config", std::process::id()));
+        env::set_var("XDG_CONFIG_HOME", missing);
+        for name in [
+            "N_PACKAGE_MANAGER",
+            "N_PROFILE",
+            "npm_config_user_agent",
+            "N_DEPTH",
+            "NODE_ENV",
+            "CI",
+        ]
+        .iter()
+        .chain(CI_VARIABLES)
+        {
+            env::remove_var(name);
+        }
+    });
+}
+
+// A fresh directory under the system temp dir, removed again on drop
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        isolate_env();
+        let path = env::temp_dir().join(format!(
+            "n-test-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path).expect("failed to create temp dir");
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    // Writes `contents` to `name` inside the directory, creating parent directories
+    pub fn write(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("failed to create parent dir");
+        }
+        fs::write(&path, contents).expect("failed to write file");
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// Backdates the modification time of `path`
+pub fn set_modified(path: &Path, seconds_ago: u64) {
+    let modified = SystemTime::now() - Duration::from_secs(seconds_ago);
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(modified))
+        .expect("failed to set modification time");
+}
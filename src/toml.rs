@@ -0,0 +1,465 @@
+// A small TOML reader covering what n's config files need: tables, dotted and
+// quoted keys, strings, integers, booleans, arrays and inline tables. Written
+// documents use the same subset, comments don't survive a round trip.
+
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+pub type Table = BTreeMap<String, Value>;
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Table, String> {
+    let mut parser = Parser {
+        chars: input.chars().peekable(),
+        line: 1,
+    };
+    parser.parse_document()
+}
+
+// Recursively merges `overlay` into `base`, tables are merged and everything else replaced
+pub fn merge(base: &mut Table, overlay: &Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(existing)), Value::Table(incoming)) => merge(existing, incoming),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+// The value at a dotted path like `profiles.ci.autocorrect`
+pub fn get_path<'t>(table: &'t Table, path: &str) -> Option<&'t Value> {
+    let (parents, key) = split_path(path);
+
+    let mut current = table;
+    for parent in parents {
+        current = current.get(parent)?.as_table()?;
+    }
+    current.get(key)
+}
+
+// Sets the value at a dotted path, creating the tables along the way
+pub fn set_path(table: &mut Table, path: &str, value: Value) -> Result<(), String> {
+    let (parents, key) = split_path(path);
+    if key.is_empty() || parents.iter().any(|parent| parent.is_empty()) {
+        return Err(format!("invalid key `{}`", path));
+    }
+
+    let mut current = table;
+    for parent in parents {
+        let entry = current
+            .entry(parent.to_string())
+            .or_insert_with(|| Value::Table(Table::new()));
+
+        current = match entry {
+            Value::Table(table) => table,
+            _ => return Err(format!("`{}` is not a table", parent)),
+        };
+    }
+
+    current.insert(key.to_string(), value);
+    Ok(())
+}
+
+fn split_path(path: &str) -> (Vec<&str>, &str) {
+    let mut parts: Vec<&str> = path.split('.').collect();
+    let key = parts.pop().unwrap_or_default();
+    (parts, key)
+}
+
+// Renders a whole document, plain values first and every table as its own section
+pub fn to_string(table: &Table) -> String {
+    let mut output = String::new();
+    write_section(&mut output, &[], table);
+    output
+}
+
+fn write_section(output: &mut String, path: &[String], table: &Table) {
+    let (tables, values): (Vec<_>, Vec<_>) = table
+        .iter()
+        .partition(|(_, value)| matches!(value, Value::Table(_)));
+
+    // A table holding only tables is implied by their headers
+    if !path.is_empty() && (!values.is_empty() || tables.is_empty()) {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        let keys: Vec<String> = path.iter().map(|key| format_key(key)).collect();
+        output.push_str(&format!("[{}]\n", keys.join(".")));
+    }
+
+    for (key, value) in values {
+        output.push_str(&format!("{} = {}\n", format_key(key), format_value(value)));
+    }
+
+    for (key, value) in tables {
+        if let Value::Table(nested) = value {
+            let mut path = path.to_vec();
+            path.push(key.clone());
+            write_section(output, &path, nested);
+        }
+    }
+}
+
+pub fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(value) => format_string(value),
+        Value::Integer(value) => value.to_string(),
+        Value::Boolean(value) => value.to_string(),
+        Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(format_value).collect();
+            format!("[{}]", values.join(", "))
+        }
+        Value::Table(table) if table.is_empty() => "{}".to_string(),
+        Value::Table(table) => {
+            let entries: Vec<String> = table
+                .iter()
+                .map(|(key, value)| format!("{} = {}", format_key(key), format_value(value)))
+                .collect();
+            format!("{{ {} }}", entries.join(", "))
+        }
+    }
+}
+
+pub fn format_key(key: &str) -> String {
+    let is_bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+    if is_bare {
+        key.to_string()
+    } else {
+        format_string(key)
+    }
+}
+
+fn format_string(value: &str) -> String {
+    let mut formatted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => formatted.push_str("\\\""),
+            '\\' => formatted.push_str("\\\\"),
+            '\n' => formatted.push_str("\\n"),
+            '\t' => formatted.push_str("\\t"),
+            '\r' => formatted.push_str("\\r"),
+            c => formatted.push(c),
+        }
+    }
+    formatted.push('"');
+    formatted
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn parse_document(&mut self) -> Result<Table, String> {
+        let mut root = Table::new();
+        let mut current: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_blank_lines();
+
+            match self.chars.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.chars.next();
+                    if self.chars.peek() == Some(&'[') {
+                        return Err(self.error("arrays of tables are not supported"));
+                    }
+                    current = self.parse_key()?;
+                    self.expect(']')?;
+                    table_at(&mut root, &current).map_err(|e| self.error(&e))?;
+                }
+                Some(_) => {
+                    let key = self.parse_key()?;
+                    self.expect('=')?;
+                    let value = self.parse_value()?;
+
+                    let mut path = current.clone();
+                    path.extend(key);
+                    insert(&mut root, &path, value).map_err(|e| self.error(&e))?;
+                }
+            }
+
+            self.expect_line_end()?;
+        }
+    }
+
+    fn parse_key(&mut self) -> Result<Vec<String>, String> {
+        let mut segments = Vec::new();
+
+        loop {
+            self.skip_spaces();
+
+            let segment = match self.chars.peek() {
+                Some('"') => self.parse_basic_string()?,
+                Some('\'') => self.parse_literal_string()?,
+                _ => {
+                    let mut segment = String::new();
+                    while let Some(&c) = self.chars.peek() {
+                        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                            segment.push(c);
+                            self.chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    if segment.is_empty() {
+                        return Err(self.error("expected a key"));
+                    }
+                    segment
+                }
+            };
+            segments.push(segment);
+
+            self.skip_spaces();
+            if self.chars.peek() == Some(&'.') {
+                self.chars.next();
+            } else {
+                return Ok(segments);
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, String> {
+        self.skip_spaces();
+
+        match self.chars.peek() {
+            Some('"') => self.parse_basic_string().map(Value::String),
+            Some('\'') => self.parse_literal_string().map(Value::String),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_inline_table(),
+            Some('t') | Some('f') => self.parse_boolean(),
+            Some(c) if c.is_ascii_digit() || *c == '-' || *c == '+' => self.parse_integer(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn parse_basic_string(&mut self) -> Result<String, String> {
+        self.chars.next();
+        let mut value = String::new();
+
+        loop {
+            match self.chars.next() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(value),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('u') => {
+                        let code: String = (0..4).filter_map(|_| self.chars.next()).collect();
+                        let c = u32::from_str_radix(&code, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| self.error("invalid unicode escape"))?;
+                        value.push(c);
+                    }
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn parse_literal_string(&mut self) -> Result<String, String> {
+        self.chars.next();
+        let mut value = String::new();
+
+        loop {
+            match self.chars.next() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some('\'') => return Ok(value),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, String> {
+        self.chars.next();
+        let mut values = Vec::new();
+
+        loop {
+            self.skip_blank_lines();
+            if self.chars.peek() == Some(&']') {
+                self.chars.next();
+                return Ok(Value::Array(values));
+            }
+
+            values.push(self.parse_value()?);
+
+            self.skip_blank_lines();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(values)),
+                _ => return Err(self.error("expected `,` or `]` in array")),
+            }
+        }
+    }
+
+    fn parse_inline_table(&mut self) -> Result<Value, String> {
+        self.chars.next();
+        let mut table = Table::new();
+
+        self.skip_spaces();
+        if self.chars.peek() == Some(&'}') {
+            self.chars.next();
+            return Ok(Value::Table(table));
+        }
+
+        loop {
+            let key = self.parse_key()?;
+            self.expect('=')?;
+            let value = self.parse_value()?;
+            insert(&mut table, &key, value).map_err(|e| self.error(&e))?;
+
+            self.skip_spaces();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Table(table)),
+                _ => return Err(self.error("expected `,` or `}` in inline table")),
+            }
+        }
+    }
+
+    fn parse_boolean(&mut self) -> Result<Value, String> {
+        let word = self.take_word();
+        match word.as_str() {
+            "true" => Ok(Value::Boolean(true)),
+            "false" => Ok(Value::Boolean(false)),
+            _ => Err(self.error(&format!("unexpected `{}`", word))),
+        }
+    }
+
+    fn parse_integer(&mut self) -> Result<Value, String> {
+        let word = self.take_word();
+        word.replace('_', "")
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| self.error(&format!("invalid integer `{}`", word)))
+    }
+
+    fn take_word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '+' {
+                word.push(c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        word
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_spaces();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected `{}`", expected))),
+        }
+    }
+
+    fn expect_line_end(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        match self.chars.peek() {
+            None | Some('\n') | Some('\r') | Some('#') => Ok(()),
+            _ => Err(self.error("expected end of line")),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while let Some(' ') | Some('\t') = self.chars.peek() {
+            self.chars.next();
+        }
+    }
+
+    // Skips whitespace, newlines and comments
+    fn skip_blank_lines(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                ' ' | '\t' | '\r' => {
+                    self.chars.next();
+                }
+                '\n' => {
+                    self.line += 1;
+                    self.chars.next();
+                }
+                '#' => {
+                    while !matches!(self.chars.peek(), None | Some('\n')) {
+                        self.chars.next();
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line, message)
+    }
+}
+
+fn table_at<'t>(root: &'t mut Table, path: &[String]) -> Result<&'t mut Table, String> {
+    let mut table = root;
+    for segment in path {
+        let entry = table
+            .entry(segment.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        table = match entry {
+            Value::Table(inner) => inner,
+            _ => return Err(format!("`{}` is not a table", segment)),
+        };
+    }
+    Ok(table)
+}
+
+fn insert(root: &mut Table, path: &[String], value: Value) -> Result<(), String> {
+    let (last, parents) = path.split_last().expect("keys have at least one segment");
+    let table = table_at(root, parents)?;
+
+    if table.contains_key(last) {
+        return Err(format!("duplicate key `{}`", last));
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
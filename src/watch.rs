@@ -0,0 +1,79 @@
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::detect::{resolve_manager, Detection};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// A change has to hold this long before it's reported, so a branch switch that
+// deletes and recreates lockfiles only produces one event
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+// Re-runs detection whenever the result changes and hands every settled result to
+// `on_change`, starting with the current one. Returns once `on_change` returns false.
+pub fn watch_detection(
+    dir: &Path,
+    config: &Config,
+    mut on_change: impl FnMut(Option<&Detection>) -> bool,
+) {
+    let mut reported = resolve_manager(dir, config, None);
+    if !on_change(reported.as_ref()) {
+        return;
+    }
+
+    let mut pending: Option<(Option<Detection>, Instant)> = None;
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let current = resolve_manager(dir, config, None);
+
+        if current == reported {
+            pending = None;
+            continue;
+        }
+
+        match &pending {
+            Some((candidate, since)) if *candidate == current => {
+                if since.elapsed() >= DEBOUNCE {
+                    reported = current;
+                    pending = None;
+                    if !on_change(reported.as_ref()) {
+                        return;
+                    }
+                }
+            }
+            _ => pending = Some((current, Instant::now())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package_manager::PackageManager;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn new_lockfile_is_reported() {
+        let dir = TempDir::new();
+        dir.write("yarn.lock", "");
+        let mut seen = Vec::new();
+
+        watch_detection(dir.path(), &Config::default(), |detection| {
+            seen.push(detection.map(|detection| detection.manager));
+            if seen.len() == 1 {
+                fs::remove_file(dir.path().join("yarn.lock")).unwrap();
+                dir.write("pnpm-lock.yaml", "");
+            }
+            seen.len() < 2
+        });
+
+        assert_eq!(
+            seen,
+            [Some(PackageManager::Yarn), Some(PackageManager::Pnpm)]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::json;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspacePackage {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+// Lists the packages declared by the workspace rooted at `root`, either through the
+// `workspaces` field in package.json or a pnpm-workspace.yaml
+pub fn enumerate_workspaces(root: &Path) -> Vec<WorkspacePackage> {
+    let patterns = read_workspace_patterns(root);
+
+    let (excludes, includes): (Vec<&String>, Vec<&String>) = patterns
+        .iter()
+        .partition(|pattern| pattern.starts_with('!'));
+
+    let excluded: Vec<PathBuf> = excludes
+        .iter()
+        .flat_map(|pattern| expand_glob(root, &pattern[1..]))
+        .collect();
+
+    let mut dirs: Vec<PathBuf> = includes
+        .iter()
+        .flat_map(|pattern| expand_glob(root, pattern))
+        .filter(|dir| dir.join("package.json").is_file() && !excluded.contains(dir))
+        .collect();
+
+    dirs.sort();
+    dirs.dedup();
+
+    dirs.into_iter()
+        .map(|dir| WorkspacePackage {
+            name: read_package_name(&dir),
+            dir,
+        })
+        .collect()
+}
+
+pub fn read_package_name(dir: &Path) -> String {
+    fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|contents| json::parse(&contents).ok())
+        .and_then(|package_json| package_json.get("name")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+}
+
+const DEPENDENCY_FIELDS: &[&str] = &[
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+// Names from `dependencies` and then `devDependencies`, each once
+pub fn read_dependencies(manifest: &Path) -> Vec<String> {
+    let Some(package_json) = fs::read_to_string(manifest)
+        .ok()
+        .and_then(|contents| json::parse(&contents).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut dependencies: Vec<String> = Vec::new();
+    for field in ["dependencies", "devDependencies"] {
+        let names = package_json
+            .get(field)
+            .and_then(|deps| deps.as_object())
+            .unwrap_or_default();
+
+        for (name, _) in names {
+            if !dependencies.contains(name) {
+                dependencies.push(name.clone());
+            }
+        }
+    }
+
+    dependencies
+}
+
+// Workspace packages whose package.json still lists `dependency`
+pub fn find_references(root: &Path, dependency: &str) -> Vec<WorkspacePackage> {
+    enumerate_workspaces(root)
+        .into_iter()
+        .filter(|package| lists_dependency(&package.dir, dependency))
+        .collect()
+}
+
+fn lists_dependency(dir: &Path, dependency: &str) -> bool {
+    let Some(package_json) = fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|contents| json::parse(&contents).ok())
+    else {
+        return false;
+    };
+
+    DEPENDENCY_FIELDS.iter().any(|field| {
+        package_json
+            .get(field)
+            .and_then(|deps| deps.get(dependency))
+            .is_some()
+    })
+}
+
+fn read_workspace_patterns(root: &Path) -> Vec<String> {
+    if let Ok(contents) = fs::read_to_string(root.join("pnpm-workspace.yaml")) {
+        return parse_pnpm_workspace(&contents);
+    }
+
+    let Some(package_json) = fs::read_to_string(root.join("package.json"))
+        .ok()
+        .and_then(|contents| json::parse(&contents).ok())
+    else {
+        return Vec::new();
+    };
+
+    // Either `"workspaces": [...]` or yarn's `"workspaces": { "packages": [...] }`
+    let workspaces = package_json.get("workspaces");
+    let patterns = workspaces
+        .and_then(|workspaces| workspaces.as_array())
+        .or_else(|| workspaces?.get("packages")?.as_array())
+        .unwrap_or_default();
+
+    patterns
+        .iter()
+        .filter_map(|pattern| pattern.as_str().map(str::to_string))
+        .collect()
+}
+
+// Reads the `packages:` list, the only part of pnpm-workspace.yaml we care about
+fn parse_pnpm_workspace(contents: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut in_packages = false;
+
+    for line in contents.lines() {
+        let trimmed = line.split(" #").next().unwrap_or_default().trim();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if !line.starts_with(' ') && !line.starts_with('-') {
+            in_packages = trimmed == "packages:";
+            continue;
+        }
+
+        if let Some(item) = trimmed.strip_prefix('-').filter(|_| in_packages) {
+            patterns.push(
+                item.trim()
+                    .trim_matches(|c| c == '"' || c == '\'')
+                    .to_string(),
+            );
+        }
+    }
+
+    patterns
+}
+
+// Expands a workspace glob to directories. Supports `*` within a path segment and `**`
+// for any number of segments.
+pub fn expand_glob(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let segments: Vec<&str> = pattern
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect();
+
+    let mut matches = Vec::new();
+    expand_segments(root.to_path_buf(), &segments, &mut matches);
+    matches
+}
+
+fn expand_segments(dir: PathBuf, segments: &[&str], matches: &mut Vec<PathBuf>) {
+    let Some((segment, rest)) = segments.split_first() else {
+        matches.push(dir);
+        return;
+    };
+
+    if *segment == "**" {
+        expand_segments(dir.clone(), rest, matches);
+        for child in child_dirs(&dir) {
+            expand_segments(child, segments, matches);
+        }
+        return;
+    }
+
+    if !segment.contains('*') {
+        let child = dir.join(segment);
+        if child.is_dir() {
+            expand_segments(child, rest, matches);
+        }
+        return;
+    }
+
+    for child in child_dirs(&dir) {
+        let name = child
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        if wildcard_match(segment, &name) {
+            expand_segments(child, rest, matches);
+        }
+    }
+}
+
+fn child_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_dir()
+                && path.file_name().is_some_and(|name| {
+                    name != "node_modules" && !name.to_string_lossy().starts_with('.')
+                })
+        })
+        .collect();
+
+    dirs.sort();
+    dirs
+}
+
+// Matches a single path segment against a pattern where `*` stands for any run of characters
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+
+    if !name.starts_with(first) || !name[first.len()..].ends_with(last) {
+        return false;
+    }
+
+    let mut remaining = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match remaining.find(part) {
+            Some(index) => remaining = &remaining[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    // A root with `packages/web` depending on lodash and `packages/api` that doesn't
+    fn workspace() -> TempDir {
+        let dir = TempDir::new();
+        dir.write("package.json", r#"{"workspaces":["packages/*"]}"#);
+        dir.write(
+            "packages/web/package.json",
+            r#"{"name":"@acme/web","devDependencies":{"lodash":"^4.0.0"}}"#,
+        );
+        dir.write(
+            "packages/api/package.json",
+            r#"{"name":"@acme/api","dependencies":{"express":"^4.0.0"}}"#,
+        );
+        dir
+    }
+
+    #[test]
+    fn orphan_scan_finds_the_referencing_package() {
+        let dir = workspace();
+
+        let references = find_references(dir.path(), "lodash");
+
+        assert_eq!(
+            references,
+            [WorkspacePackage {
+                name: "@acme/web".to_string(),
+                dir: dir.path().join("packages/web"),
+            }]
+        );
+        assert_eq!(find_references(dir.path(), "react"), []);
+    }
+
+    #[test]
+    fn dependencies_come_from_both_sections() {
+        let dir = TempDir::new();
+        let manifest = dir.write(
+            "package.json",
+            r#"{
+                "name": "app",
+                "dependencies": {"react": "^18.2.0", "lodash": "^4.17.21"},
+                "devDependencies": {"typescript": "^5.4.0", "lodash": "^4.17.21"},
+                "peerDependencies": {"react-dom": "^18.2.0"}
+            }"#,
+        );
+
+        assert_eq!(
+            read_dependencies(&manifest),
+            ["react", "lodash", "typescript"]
+        );
+        assert!(read_dependencies(&dir.path().join("missing.json")).is_empty());
+    }
+}